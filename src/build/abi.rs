@@ -0,0 +1,228 @@
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::{load_config, load_effective_for, save_config, AbiDump, AbiSymbol, CONFIG};
+use crate::{crab_err, crab_log, crab_print, crab_status};
+use super::binary::BuildProfile;
+use super::library::{CrabLib, LibKind, SharedNames};
+
+// Различия экспортируемых символов между снимком и текущей сборкой
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct AbiDiff {
+    pub removed: Vec<String>,
+    pub changed: Vec<(String, AbiSymbol, AbiSymbol)>,
+    pub added: Vec<String>,
+}
+
+impl AbiDiff {
+    fn is_breaking(&self) -> bool {
+        !self.removed.is_empty() || !self.changed.is_empty()
+    }
+}
+
+pub struct CrabAbi;
+
+impl Default for CrabAbi {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CrabAbi {
+    pub fn new() -> Self {
+        CrabAbi
+    }
+
+    // Сборка динамической библиотеки и путь к её настоящему файлу
+    fn build_shared(&self, profile: BuildProfile) -> std::io::Result<(PathBuf, String, String)> {
+        if cfg!(windows) {
+            crab_err!(ErrorKind::Unsupported, "ABI checks are not supported on Windows yet");
+        }
+
+        CrabLib::new().build_lib(LibKind::Dynamic, profile.clone())?;
+
+        let config = load_effective_for(&profile.dir())?;
+        let name = config.library.output_name(&config.project.name);
+        let names = SharedNames::new(&name, &config.project.version);
+        let path = LibKind::Dynamic.output_dir(&profile).join(&names.real);
+
+        if !path.exists() {
+            crab_err!(ErrorKind::NotFound, "Shared library not found: {}", path.display());
+        }
+
+        Ok((path, name, config.project.version))
+    }
+
+    // Экспортируемые (определённые) символы библиотеки через nm
+    fn exported_symbols(&self, lib: &Path) -> std::io::Result<BTreeMap<String, AbiSymbol>> {
+        let output = if cfg!(target_os = "macos") {
+            Command::new("nm").arg("-gU").arg(lib).output()
+        } else {
+            Command::new("nm").args(["-D", "--defined-only", "-S"]).arg(lib).output()
+        };
+
+        let output = output.map_err(|e| std::io::Error::new(e.kind(), format!("Failed to run nm: {}", e)))?;
+
+        if !output.status.success() {
+            crab_err!(ErrorKind::Other, "nm failed: {}", String::from_utf8_lossy(&output.stderr));
+        }
+
+        Ok(parse_nm(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    // crab abi dump: записать экспортируемые символы текущей сборки в abi.toml
+    pub fn dump(&self, profile: BuildProfile) -> std::io::Result<()> {
+        let (lib, name, version) = self.build_shared(profile)?;
+        let symbols = self.exported_symbols(&lib)?;
+        let count = symbols.len();
+
+        let dump = AbiDump { library: name, version, symbols };
+        save_config(&dump, CONFIG.abi_file)?;
+
+        crab_log!("INFO", "ABI", "Wrote {} symbols to {}", count, CONFIG.abi_file);
+        crab_status!("Dumped", "{} symbols to {}", count, CONFIG.abi_file);
+
+        Ok(())
+    }
+
+    // crab abi check: сравнить текущую сборку со снимком. Удаление или изменение
+    // символа без повышения мажорной версии project.version — ошибка.
+    pub fn check(&self, profile: BuildProfile) -> std::io::Result<()> {
+        if !Path::new(CONFIG.abi_file).exists() {
+            crab_err!(ErrorKind::NotFound, "{} not found, record a baseline with `crab abi dump`", CONFIG.abi_file);
+        }
+
+        let baseline: AbiDump = load_config(CONFIG.abi_file)?;
+        let (lib, _, version) = self.build_shared(profile)?;
+        let current = self.exported_symbols(&lib)?;
+
+        let diff = compare(&baseline.symbols, &current);
+
+        for s in &diff.removed {
+            crab_print!(red, "{:>12} {}", "Removed", s);
+        }
+        for (s, old, new) in &diff.changed {
+            crab_print!(red, "{:>12} {} ({} -> {})", "Changed", s, describe(old), describe(new));
+        }
+        for s in &diff.added {
+            crab_print!(green, "{:>12} {}", "Added", s);
+        }
+
+        if !diff.is_breaking() {
+            crab_status!("Checked", "ABI compatible with {} ({} added)", baseline.version, diff.added.len());
+            return Ok(());
+        }
+
+        if major_bumped(&baseline.version, &version) {
+            crab_status!("Checked", "breaking changes allowed by major bump {} -> {}", baseline.version, version);
+            crab_print!(yellow, "Run `crab abi dump` to record the new baseline");
+            return Ok(());
+        }
+
+        crab_err!(
+            ErrorKind::Other,
+            "ABI break: {} removed, {} changed without a major version bump ({} -> {})",
+            diff.removed.len(), diff.changed.len(), baseline.version, version
+        );
+    }
+}
+
+// Разбор вывода nm: "<адрес> [<размер>] <тип> <имя>"
+pub(crate) fn parse_nm(output: &str) -> BTreeMap<String, AbiSymbol> {
+    let mut symbols = BTreeMap::new();
+
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        let (size, kind, name) = match fields.as_slice() {
+            [_, size, kind, name] => (u64::from_str_radix(size, 16).ok(), *kind, *name),
+            [_, kind, name] => (None, *kind, *name),
+            _ => continue,
+        };
+
+        // служебные символы линковщика не относятся к ABI
+        if kind == "A" || kind == "U" || name.starts_with("_init") || name.starts_with("_fini") {
+            continue;
+        }
+
+        // размер важен только для данных: у функций он меняется при любой правке кода
+        let is_data = matches!(kind, "D" | "B" | "R" | "V" | "G" | "S");
+        symbols.insert(name.to_string(), AbiSymbol { kind: kind.to_string(), size: size.filter(|_| is_data) });
+    }
+
+    symbols
+}
+
+pub(crate) fn compare(old: &BTreeMap<String, AbiSymbol>, new: &BTreeMap<String, AbiSymbol>) -> AbiDiff {
+    let mut diff = AbiDiff::default();
+
+    for (name, sym) in old {
+        match new.get(name) {
+            None => diff.removed.push(name.clone()),
+            Some(cur) if cur != sym => diff.changed.push((name.clone(), sym.clone(), cur.clone())),
+            Some(_) => {}
+        }
+    }
+
+    diff.added = new.keys().filter(|n| !old.contains_key(*n)).cloned().collect();
+    diff
+}
+
+fn describe(sym: &AbiSymbol) -> String {
+    match sym.size {
+        Some(size) => format!("{} {} bytes", sym.kind, size),
+        None => sym.kind.clone(),
+    }
+}
+
+// Повышена ли мажорная версия: "1.4.2" -> "2.0.0"
+pub(crate) fn major_bumped(old: &str, new: &str) -> bool {
+    let major = |v: &str| v.trim().split('.').next().and_then(|m| m.parse::<u64>().ok());
+    matches!((major(old), major(new)), (Some(o), Some(n)) if n > o)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NM: &str = "\
+0000000000001139 0000000000000020 T _ZN5lib335helloEv
+0000000000004010 0000000000000010 D table
+0000000000001100 T _init
+0000000000001172 000000000000000b T c_api
+";
+
+    #[test]
+    fn parse_nm_keeps_data_sizes_only() {
+        let symbols = parse_nm(NM);
+        assert_eq!(symbols.len(), 3);
+        assert_eq!(symbols["c_api"], AbiSymbol { kind: "T".to_string(), size: None });
+        assert_eq!(symbols["table"], AbiSymbol { kind: "D".to_string(), size: Some(16) });
+    }
+
+    #[test]
+    fn compare_reports_removed_changed_and_added() {
+        let old = parse_nm(NM);
+        let mut new = parse_nm(NM);
+        new.remove("c_api");
+        new.insert("table".to_string(), AbiSymbol { kind: "D".to_string(), size: Some(32) });
+        new.insert("c_api2".to_string(), AbiSymbol { kind: "T".to_string(), size: None });
+
+        let diff = compare(&old, &new);
+        assert_eq!(diff.removed, vec!["c_api"]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].0, "table");
+        assert_eq!(diff.added, vec!["c_api2"]);
+        assert!(diff.is_breaking());
+    }
+
+    #[test]
+    fn only_a_higher_major_allows_breaks() {
+        assert!(major_bumped("1.4.2", "2.0.0"));
+        assert!(!major_bumped("1.4.2", "1.5.0"));
+        assert!(!major_bumped("0.0.1", "0.1.0"));
+        assert!(!major_bumped("2.0.0", "1.0.0"));
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::fs::{self, File};
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
@@ -5,10 +6,17 @@ use std::process::Command;
 use std::time::Instant;
 use rayon::prelude::*;
 
-use crate::config::{load_config, CrabConfig, CONFIG};
+use crate::config::{load_effective_for, CrabConfig, CONFIG};
 use crate::find::CrabFind;
 use crate::{crab_err, crab_log, crab_status};
-use super::helpers::CrabBuildFunc;
+use super::check::CrabCheck;
+use super::helpers::{is_assembly, CrabBuildFunc};
+use super::library::{CrabLib, LibKind};
+use super::cxx_modules::{self, ModuleGraph};
+use super::launcher::{self, Launcher};
+use super::pch::{self, Pch};
+use super::unity;
+use super::targets::BinSelection;
 use std::io::ErrorKind;
 
 // Профиль сборки: отличаются каталогом и набором флагов компиляции/линковки
@@ -39,6 +47,18 @@ impl BuildProfile {
         }
     }
 
+    // Флаги компиляции объектов библиотеки (lib = None — обычная сборка бинарника)
+    pub(crate) fn library_compile_flags(&self, lib: Option<LibKind>) -> Vec<&'static str> {
+        let mut flags = self.compile_flags().to_vec();
+        match lib {
+            Some(LibKind::Static) => flags.retain(|f| *f != "-flto"),
+            // -fPIC нужен только на Unix; на Windows он бессмысленен и вызывает предупреждение
+            Some(LibKind::Dynamic) if !cfg!(windows) => flags.push("-fPIC"),
+            _ => {}
+        }
+        flags
+    }
+
     // Флаги линковки
     pub(crate) fn link_flags(&self) -> &'static [&'static str] {
         match self {
@@ -61,7 +81,9 @@ impl BuildProfile {
     }
 }
 
-pub struct CrabBuild;
+pub struct CrabBuild {
+    pub(crate) unity: bool,
+}
 
 impl Default for CrabBuild {
     fn default() -> Self {
@@ -71,12 +93,18 @@ impl Default for CrabBuild {
 
 impl CrabBuild {
     pub fn new() -> Self {
-        CrabBuild
+        CrabBuild { unity: false }
+    }
+
+    // crab build --unity: объединять исходники даже без [build] unity
+    pub fn with_unity(mut self, unity: bool) -> Self {
+        self.unity = unity;
+        self
     }
 
     // Чтение файла с путями для сторонних библиотек -> список флагов -I (по одному на аргумент)
-    pub(crate) fn read_include_files_and_fmt(&self) -> std::io::Result<Vec<String>> {
-        let path = PathBuf::from(CONFIG.build_dir).join(CONFIG.data_dir).join(CONFIG.include_file);
+    pub(crate) fn read_include_files_and_fmt(&self, data: &Path) -> std::io::Result<Vec<String>> {
+        let path = data.join(CONFIG.include_file);
 
         if !path.exists() {
             return Ok(Vec::new());
@@ -105,8 +133,8 @@ impl CrabBuild {
     // Поддерживает два формата строк:
     //   - сырые флаги "-L/path" и "-lname" (из pkg-config)
     //   - полные пути к файлам библиотек (из fallback-поиска по dir компилятора)
-    pub(crate) fn read_lib_path_and_fmt(&self) -> std::io::Result<(Vec<String>, Vec<String>)> {
-        let path_to_file = PathBuf::from(CONFIG.build_dir).join(CONFIG.data_dir).join(CONFIG.lib_file);
+    pub(crate) fn read_lib_path_and_fmt(&self, data: &Path) -> std::io::Result<(Vec<String>, Vec<String>)> {
+        let path_to_file = data.join(CONFIG.lib_file);
 
         if !path_to_file.exists() {
             return Ok((Vec::new(), Vec::new()));
@@ -159,7 +187,7 @@ impl CrabBuild {
 
     // Имя библиотеки для флага -l из имени файла:
     // отбрасываем расширение (включая .dll.a) и ведущий "lib"
-    fn lib_link_name(file_name: &str) -> String {
+    pub(crate) fn lib_link_name(file_name: &str) -> String {
         let mut name = file_name;
         for ext in [".dll.a", ".dylib", ".so", ".dll", ".lib", ".a"] {
             if let Some(stripped) = name.strip_suffix(ext) {
@@ -170,74 +198,167 @@ impl CrabBuild {
         name.strip_prefix("lib").unwrap_or(name).to_string()
     }
 
-    // Компиляция исходников в объектные файлы
-    fn compile_to_object(&self, profile: &BuildProfile, path_dep: &Path, path_obj: &Path, is_find: bool, changed: &[String]) -> std::io::Result<()> {
+    // Флаги [library] для единицы трансляции: объекты самой библиотеки (lib = Some)
+    // или код, который её использует, если библиотека — цель проекта
+    fn lib_args(config: &CrabConfig, lib: Option<LibKind>) -> Vec<String> {
+        match lib {
+            Some(kind) => kind.compile_args(config),
+            None => LibKind::from_config(config).map(|k| k.consumer_args(config)).unwrap_or_default(),
+        }
+    }
+
+    // Подпись сборки: компилятор и язык, все флаги профиля, [build] и окружения, а также
+    // -I/-L найденных сторонних библиотек (find). Хранится рядом с состоянием объектов;
+    // её смена вызывает полную пересборку.
+    pub(crate) fn signature(&self, config: &CrabConfig, profile: &BuildProfile, find: Option<&Path>, lib: Option<LibKind>) -> std::io::Result<String> {
+        let mut parts = vec![config.settings.compiler.clone(), config.settings.lang.clone()];
+        parts.extend(profile.library_compile_flags(lib).iter().map(|s| s.to_string()));
+        parts.extend(Self::lib_args(config, lib));
+        parts.extend(config.build.compile_args());
+        parts.extend(pch::header_args(&config.build));
+        for o in &config.build.overrides {
+            parts.push(format!("[{}]", o.pattern));
+            parts.extend(o.defines.iter().cloned());
+            parts.extend(o.include_dirs.iter().cloned());
+            parts.extend(o.cflags.iter().cloned());
+        }
+        parts.extend(profile.sanitizer_flags());
+        parts.extend(profile.link_flags().iter().map(|s| s.to_string()));
+        parts.extend(config.build.link_args());
+        if let Some(data) = find {
+            parts.extend(self.read_include_files_and_fmt(data)?);
+            let (lib_paths, lib_names) = self.read_lib_path_and_fmt(data)?;
+            parts.extend(lib_paths);
+            parts.extend(lib_names);
+        }
+        Ok(parts.join(" "))
+    }
+
+    // Компиляция исходников в объектные файлы каталога сборки base (obj/ и файл зависимостей).
+    // lib — сборка объектов библиотеки: для динамической добавляется -fPIC, для
+    // статической убирается -flto (архив должен линковаться и без LTO).
+    // config — итоговый конфиг сборки (с наложенными флагами цели [[bin]] или модуля).
+    pub(crate) fn compile_to_object(&self, config: &CrabConfig, profile: &BuildProfile, base: &Path, find: Option<&Path>, changed: &[String], lib: Option<LibKind>) -> std::io::Result<()> {
         crab_log!("INFO", "BUILD", "Compilation to an object file");
-        let config: CrabConfig = load_config(CONFIG.config_file)?;
+        let path_dep = base.join(CONFIG.dependencies);
+        let path_obj = base.join(CONFIG.object_dir);
         let cbf = CrabBuildFunc::new();
 
-        let compiler = config.settings.compiler;
-        let head = config.settings.header_dir;
-        let lang = config.settings.lang;
-        let is_head = cbf.is_header()?;
+        let lib_args = Self::lib_args(config, lib);
+        let compiler = &config.settings.compiler;
+        let head = &config.settings.header_dir;
+        let lang = &config.settings.lang;
+        let is_head = cbf.is_header(config)?;
 
         if !path_dep.exists() {
             crab_log!("ERROR", "BUILD", "The dependency file was not found: {}", path_dep.display());
             crab_err!(ErrorKind::NotFound, "The dependency file was not found");
         }
 
-        let flags = profile.compile_flags();
+        let flags = profile.library_compile_flags(lib);
         let user_compile = config.build.compile_args();
-        crab_log!("INFO", "BUILD", "Flags for compiling: {:?} {:?}", flags, user_compile);
+        crab_log!("INFO", "BUILD", "Flags for compiling: {:?} {:?} {:?}", flags, lib_args, user_compile);
+
+        // ccache/sccache: проверяем заранее и запоминаем счётчики для сводки
+        let launcher = Launcher::resolve(&config.build)?;
+        let cache_before = launcher.as_ref().and_then(|l| l.stats());
+
+        // общие флаги единиц трансляции — для pch и сканирования модулей C++20
+        let mut common_args = Vec::new();
+        if is_head {
+            common_args.push(format!("-I{}", head));
+        }
+        if let Some(data) = find {
+            common_args.extend(self.read_include_files_and_fmt(data)?);
+        }
+        common_args.extend(flags.iter().map(|s| s.to_string()));
+        common_args.extend(lib_args.iter().cloned());
+        common_args.extend(user_compile.iter().cloned());
+        common_args.extend(profile.sanitizer_flags());
+
+        // предкомпилированный заголовок собирается с теми же флагами, что и файлы
+        let pch = Pch::prepare(config, base)?;
+        if let Some(pch) = pch.as_ref().filter(|_| !changed.is_empty()) {
+            pch.build(compiler, lang, &common_args)?;
+        }
+        let pch_args = pch.map(|p| p.include_args()).unwrap_or_default();
 
-        let file = fs::File::open(path_dep)?;
+        let file = fs::File::open(&path_dep)?;
         let reader = BufReader::new(&file);
         let lines: Vec<String> = reader.lines().collect::<std::io::Result<Vec<_>>>()?;
 
-        lines.par_iter().try_for_each(|line| -> std::io::Result<()> {
-            let line = line.trim();
-
-            if line.is_empty() {
-                return Ok(());
+        // пары [объект, исходник] из .d
+        let mut units = Vec::new();
+        for line in lines.iter().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+            let result = cbf.split_dep(line, lang)?;
+            if result[0].ends_with(".o") {
+                units.push(result);
             }
+        }
 
-            let result = cbf.split_dep(line, &lang)?;
+        // модули C++20: интерфейсы собираются раньше импортирующих их файлов,
+        // а правка интерфейса пересобирает и всех импортёров
+        let sources: Vec<String> = units.iter().map(|u| u[1].clone()).collect();
+        let modules = ModuleGraph::scan(config, base, &sources, &common_args)?;
+        let (changed, waves) = match &modules {
+            Some(m) => (m.affected(changed), m.waves(&sources)?),
+            None => (changed.to_vec(), vec![sources]),
+        };
 
-            if !result[0].ends_with(".o") {
-                return Ok(());
-            }
+        for wave in waves {
+            let wave: HashSet<String> = wave.into_iter().collect();
 
-            if !changed.contains(&result[1]) {
-                crab_log!("INFO", "BUILD", "Skipping file: {}", &result[1]);
-                return Ok(());
-            }
+            units.par_iter().filter(|u| wave.contains(&u[1])).try_for_each(|result| -> std::io::Result<()> {
+                if !changed.contains(&result[1]) {
+                    crab_log!("INFO", "BUILD", "Skipping file: {}", &result[1]);
+                    return Ok(());
+                }
 
-            let path_to_obj = format!("{}/{}", path_obj.display(), result[0]);
-            crab_status!("Compiling", "{}", &result[1]);
+                let path_to_obj = format!("{}/{}", path_obj.display(), result[0]);
+                crab_status!("Compiling", "{}", &result[1]);
 
-            let mut compile_args: Vec<String> = vec![
-                "-c".to_string(),
-                result[1].clone(),
-                "-o".to_string(),
-                path_to_obj,
-            ];
+                // -x для .cppm/.ixx должен стоять перед самим файлом
+                let mut compile_args = cxx_modules::language_args(compiler, &result[1]);
+                compile_args.extend(["-c".to_string(), result[1].clone(), "-o".to_string(), path_to_obj]);
 
-            if is_head {
-                compile_args.push(format!("-I{}", head));
-            }
+                // ассемблер компилируется тем же драйвером, но без модулей, pch и -std
+                let asm = is_assembly(&result[1]);
 
-            if is_find {
-                compile_args.extend(self.read_include_files_and_fmt()?);
-            }
+                if let Some(m) = modules.as_ref().filter(|_| !asm) {
+                    compile_args.extend(m.flags());
+                    compile_args.extend(m.unit_args(&result[1]));
+                }
+
+                if !asm && (modules.is_none() || !cxx_modules::declares_module(&result[1])) {
+                    compile_args.extend(pch_args.iter().cloned());
+                }
+
+                if is_head {
+                    compile_args.push(format!("-I{}", head));
+                }
+
+                if let Some(data) = find {
+                    compile_args.extend(self.read_include_files_and_fmt(data)?);
+                }
+
+                let file_args = config.build.file_args(&result[1]);
+
+                let user_compile = user_compile.iter().filter(|a| !(asm && a.starts_with("-std=")));
 
-            cbf.output_wrapper(Command::new(&compiler).args(&compile_args).args(flags).args(&user_compile).args(profile.sanitizer_flags()).output())
-        })?;
+                cbf.output_wrapper(launcher::command(launcher.as_ref(), compiler).args(&compile_args).args(&flags).args(&lib_args).args(user_compile).args(&file_args).args(profile.sanitizer_flags()).output())
+            })?;
+        }
+
+        if let Some(l) = &launcher {
+            l.report(cache_before);
+        }
 
         Ok(())
     }
 
-    // Линковка объектных файлов в исполняемый
-    fn linking(&self, profile: &BuildProfile, path_obj: &Path, is_find: bool, mod_name: Option<&str>, bin_name: Option<&str>) -> std::io::Result<()> {
+    // Линковка объектных файлов в исполняемый (или разделяемую библиотеку модуля) out;
+    // uses — архивы модулей и core, идут сразу после объектов
+    fn linking(&self, config: &CrabConfig, profile: &BuildProfile, path_obj: &Path, out: &Path, find: Option<&Path>, uses: &[PathBuf]) -> std::io::Result<()> {
         crab_log!("INFO", "BUILD", "Linking");
         if !path_obj.exists() {
             crab_log!("ERROR", "BUILD", "The directory with the object files was not found: {}", path_obj.display());
@@ -256,34 +377,23 @@ impl CrabBuild {
             }
         }
 
-        let config: CrabConfig = load_config(CONFIG.config_file)?;
-        let compiler = config.settings.compiler;
-        let project_name = config.project.name;
+        let compiler = &config.settings.compiler;
         let link_flags = profile.link_flags();
         let user_link = config.build.link_args();
 
-        // EXE_SUFFIX = ".exe" на Windows, "" на Unix
-        let exe = std::env::consts::EXE_SUFFIX;
-        let bin_display = format!("{}{}", bin_name.unwrap_or(&project_name), exe);
-        let path_to_bin = if let (Some(m_name), Some(b_name)) = (mod_name, bin_name) {
-            format!("{}/{}/{}/{}/{}/{}{}", CONFIG.build_dir, CONFIG.module_dir, m_name, profile.dir(), CONFIG.binary_dir, b_name, exe)
-        } else {
-            format!("{}/{}/{}/{}{}", CONFIG.build_dir, profile.dir(), CONFIG.binary_dir, project_name, exe)
-        };
-
-        crab_log!("INFO", "BUILD", "Creating a path for an executable file: {}", path_to_bin);
-        crab_status!("Linking", "{}", bin_display);
+        crab_log!("INFO", "BUILD", "Creating a path for an executable file: {}", out.display());
+        crab_status!("Linking", "{}", out.file_name().and_then(|n| n.to_str()).unwrap_or_default());
 
         let san_flags = profile.sanitizer_flags();
 
-        if !is_find {
-            crab_log!("INFO", "BUILD", "Linking without third-party libraries");
-            crb.output_wrapper(Command::new(&compiler).args(&obj_files).arg("-o").arg(&path_to_bin).args(link_flags).args(&user_link).args(&san_flags).output())?;
-        } else {
-            let (paths, names) = self.read_lib_path_and_fmt()?;
+        if let Some(data) = find {
+            let (paths, names) = self.read_lib_path_and_fmt(data)?;
 
             crab_log!("INFO", "BUILD", "Linking with third-party libraries: {:?}", names);
-            crb.output_wrapper(Command::new(&compiler).args(&obj_files).arg("-o").arg(&path_to_bin).args(link_flags).args(paths).args(names).args(&user_link).args(&san_flags).output())?;
+            crb.output_wrapper(Command::new(compiler).args(&obj_files).args(uses).arg("-o").arg(out).args(link_flags).args(paths).args(names).args(&user_link).args(&san_flags).output())?;
+        } else {
+            crab_log!("INFO", "BUILD", "Linking without third-party libraries");
+            crb.output_wrapper(Command::new(compiler).args(&obj_files).args(uses).arg("-o").arg(out).args(link_flags).args(&user_link).args(&san_flags).output())?;
         }
 
         Ok(())
@@ -295,12 +405,33 @@ impl CrabBuild {
 
         crb.is_compiler()?;
 
+        let mut config = load_effective_for(&profile.dir())?;
+        unity::force(&mut config, self.unity);
+
+        // header-only: компилировать нечего, сборка — это проверка заголовков
+        if mod_name.is_none() && config.library.is_header_only() {
+            return CrabCheck::new().check_headers(&config, &profile);
+        }
+
+        // библиотека проекта и исполняемые цели [[bin]] (bin_name без модуля — --bin <имя>)
+        if mod_name.is_none() && (LibKind::from_config(&config).is_some() || !config.bins.is_empty() || bin_name.is_some()) {
+            let selection = bin_name.map_or(BinSelection::Default, BinSelection::Named);
+            return self.build_targets(&config, &profile, selection);
+        }
+
+        // флаги, язык и вид модуля поверх проекта
+        let config = match mod_name {
+            Some(m) => crate::module::module_config(config, m)?,
+            None => config,
+        };
+
         let start = Instant::now();
 
         crab_log!("INFO", "BUILD", "START {} BUILDING", profile.dir());
 
         let flag = profile.dir();
-        let is_module = mod_name.is_some() && bin_name.is_some();
+        // модуль без bin_name собирается в статический архив — для тех, кто его uses
+        let is_module = mod_name.is_some();
 
         if is_module {
             crb.create_module_dir(&flag, mod_name.unwrap())?;
@@ -308,30 +439,22 @@ impl CrabBuild {
             crb.create_build_dir(&flag)?;
         }
 
-        let config: CrabConfig = load_config(CONFIG.config_file)?;
-        let lang = config.settings.lang;
-        let source_dir = config.settings.source_dir;
+        let lang = config.settings.lang.clone();
+        let source_dir = config.settings.source_dir.clone();
         let path = Path::new(&source_dir);
 
         let mut source: Vec<String>;
 
         if is_module {
             let m_name = mod_name.unwrap();
-            let module = config.module.get(m_name).ok_or_else(|| std::io::Error::new
-                (std::io::ErrorKind::NotFound, format!("Module {} not found", m_name)))?;
-
-            source = module.dependencies.clone();
+            source = crate::module::resolve_sources(&config, m_name)?;
 
             if source.is_empty() {
-                crab_err!(ErrorKind::Other, "No files in module {}!", m_name);
+                crab_err!(ErrorKind::NotFound, "No {} sources in module {} ({})", lang, m_name, config.module[m_name].path);
             }
         } else {
             source = Vec::new();
-            if lang == "c" {
-                CrabBuildFunc::collect_file_with_extension(path, "c", &mut source)?;
-            } else {
-                CrabBuildFunc::collect_file_with_extension(path, "cpp", &mut source)?;
-            }
+            CrabBuildFunc::collect_sources(path, &lang, &mut source)?;
 
             if source.is_empty() {
                 crab_err!(ErrorKind::NotFound, "There are no files to build!");
@@ -340,27 +463,21 @@ impl CrabBuild {
 
         crb.write_file_in_config(&source)?;
 
-        crb.check_ignore_files(&mut source)?;
+        crb.check_ignore_files(&flag, &mut source)?;
 
         if source.is_empty() {
             crab_err!(ErrorKind::NotFound, "There are no files to build!");
         }
 
-        if is_module {
-            crb.write_dependencies_module(mod_name.unwrap(), &flag, &source)?;
-        } else {
-            crb.write_dependencies(&flag, &source)?;
-        }
-
-        let find = if is_module {
+        let finder = if is_module {
             let m_name = mod_name.unwrap();
             let module = config.module.get(m_name).ok_or_else(|| std::io::Error::new
                 (std::io::ErrorKind::NotFound, format!("Module {} not found", m_name)))?;
-            let mod_path = module.path.clone();
-            CrabFind::new(&mod_path).parsing_include()?
+            CrabFind::module(m_name, &module.path, &flag)
         } else {
-            CrabFind::new(".").parsing_include()?
+            CrabFind::new(".", &flag)
         };
+        let find = finder.parsing_include()?.then(|| finder.data_dir());
 
         let base = if is_module {
             PathBuf::from(CONFIG.build_dir).join(CONFIG.module_dir).join(mod_name.unwrap()).join(&flag)
@@ -368,34 +485,58 @@ impl CrabBuild {
             PathBuf::from(CONFIG.build_dir).join(&flag)
         };
 
+        let source = unity::combine(&config, &base, source)?;
+
         let path_dep = base.join(CONFIG.dependencies);
         let path_obj = base.join(CONFIG.object_dir);
         let path_obj_data = base.join(CONFIG.object_data);
 
-        let changed = crb.get_changed_files(&path_obj_data, &path_dep, &source, &lang)?;
+        let uses = match mod_name {
+            Some(m) => crate::module::uses_outputs(&config, m, &profile)?,
+            None => Vec::new(),
+        };
+
+        crb.write_dependencies(&config, &path_dep, &source, find)?;
+
+        let signature = self.signature(&config, &profile, find, None)?;
+        let changed = crb.get_changed_files(&path_obj_data, &path_dep, &source, &lang, &signature)?;
 
         // Тихий режим: если ничего не изменилось и бинарь на месте — только Finished
         let exe = std::env::consts::EXE_SUFFIX;
-        let bin_disp = if is_module { bin_name.unwrap() } else { config.project.name.as_str() };
-        let bin_path = base.join(CONFIG.binary_dir).join(format!("{}{}", bin_disp, exe));
+        let bin_path = match mod_name {
+            Some(m) => crate::module::module_output(&config, m, &profile, bin_name),
+            None => base.join(CONFIG.binary_dir).join(format!("{}{}", config.project.name, exe)),
+        };
+
+        // пересобранная зависимость из uses требует перелинковки
+        let built = bin_path.metadata().and_then(|m| m.modified()).ok();
+        let uses_newer = uses.iter().any(|u| u.metadata().and_then(|m| m.modified()).ok() > built);
 
-        if changed.is_empty() && bin_path.exists() {
+        if changed.is_empty() && !uses_newer && bin_path.exists() {
             crab_status!("Finished", "{} target in {:.2}s", flag, start.elapsed().as_secs_f64());
             return Ok(());
         }
 
-        if is_module {
-            crab_status!("Compiling", "module {} [{}]", mod_name.unwrap(), flag);
-        } else {
+        // модуль без изменений здесь только перелинковывается из-за обновлённых uses
+        if !is_module {
             crab_status!("Compiling", "{} v{} [{}]", config.project.name, config.project.version, flag);
+        } else if !changed.is_empty() {
+            crab_status!("Compiling", "module {} [{}]", mod_name.unwrap(), flag);
         }
 
-        self.compile_to_object(&profile, &path_dep, &path_obj, find, &changed)?;
+        self.compile_to_object(&config, &profile, &base, find, &changed, None)?;
 
         // Убираем .o от удалённых исходников, чтобы они не попали в линковку
         crb.prune_orphan_objects(&path_dep, &path_obj)?;
 
-        self.linking(&profile, &path_obj, find, mod_name, bin_name)?;
+        if mod_name.is_some_and(|m| crate::module::builds_archive(&config, m, bin_name)) {
+            let lib = CrabLib::new().with_unity(self.unity);
+            if lib.archive(&lib.collect_objects(&path_obj)?, &bin_path)? {
+                crab_status!("Archiving", "{}", bin_path.file_name().and_then(|n| n.to_str()).unwrap_or_default());
+            }
+        } else {
+            self.linking(&config, &profile, &path_obj, &bin_path, find, &uses)?;
+        }
 
         crab_status!("Finished", "{} target in {:.2}s", flag, start.elapsed().as_secs_f64());
         crab_log!("INFO", "BUILD", "End of the build");
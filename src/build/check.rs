@@ -1,14 +1,16 @@
-use std::io::ErrorKind;
+use std::io::{ErrorKind, Write};
 use std::path::Path;
-use std::process::Command;
+use std::process::{Command, Stdio};
 use std::time::Instant;
 use rayon::prelude::*;
 
-use crate::config::{load_config, CrabConfig, CONFIG};
+use crate::config::{load_effective_for, CrabConfig};
 use crate::find::CrabFind;
 use crate::{crab_err, crab_log, crab_status};
 use super::binary::{BuildProfile, CrabBuild};
+use super::cxx_modules;
 use super::helpers::CrabBuildFunc;
+use super::launcher::{self, Launcher};
 
 pub struct CrabCheck;
 
@@ -26,10 +28,17 @@ impl CrabCheck {
     // Синтаксическая проверка всех исходников через -fsyntax-only.
     // Никаких .o-файлов и линковки — существенно быстрее полного build.
     pub fn check(&self, release: bool) -> std::io::Result<()> {
-        let config: CrabConfig = load_config(CONFIG.config_file)?;
+        let profile = if release { BuildProfile::Release } else { BuildProfile::Debug };
+        let config = load_effective_for(&profile.dir())?;
+
+        if config.library.is_header_only() {
+            return self.check_headers(&config, &profile);
+        }
+
         let lang        = config.settings.lang.clone();
         let compiler    = config.settings.compiler.clone();
         let header_dir  = config.settings.header_dir.clone();
+        let launcher    = Launcher::resolve(&config.build)?;
 
         let ext = if lang == "c" { "c" } else { "cpp" };
         let mut sources: Vec<String> = Vec::new();
@@ -40,17 +49,27 @@ impl CrabCheck {
             crab_err!(ErrorKind::NotFound, "No source files found");
         }
 
+        // -fsyntax-only не создаёт BMI, без них импорт не проверить — такие файлы пропускаем
+        let (sources, skipped) = split_module_sources(sources);
+        if !skipped.is_empty() {
+            crab_log!("WARNING", "CHECK", "skipping C++20 module sources: {:?}", skipped);
+            crab_status!("Skipping", "{} files with C++20 modules (not supported by crab check, use crab build)", skipped.len());
+        }
+        if sources.is_empty() {
+            crab_err!(ErrorKind::Unsupported, "crab check does not support C++20 named modules, use crab build");
+        }
+
         // Сторонние библиотеки нужны для -I флагов (иначе заголовки не найдутся)
-        let find  = CrabFind::new(".").parsing_include()?;
+        let finder = CrabFind::new(".", &profile.dir());
         let build = CrabBuild::new();
-        let inc_flags = if find { build.read_include_files_and_fmt()? } else { Vec::new() };
+        let inc_flags = if finder.parsing_include()? { build.read_include_files_and_fmt(finder.data_dir())? } else { Vec::new() };
 
-        let profile = if release { BuildProfile::Release } else { BuildProfile::Debug };
         let profile_flags: Vec<String> = profile.compile_flags().iter().map(|s| s.to_string()).collect();
-        let user_flags = config.build.compile_args();
+        let mut user_flags = config.build.compile_args();
+        user_flags.extend(super::pch::header_args(&config.build));
 
         let cbf    = CrabBuildFunc::new();
-        let is_head = cbf.is_header()?;
+        let is_head = cbf.is_header(&config)?;
 
         crab_status!("Checking", "{} v{} [{}]", config.project.name, config.project.version, profile.dir());
         crab_log!("INFO", "CHECK", "checking {} files", sources.len());
@@ -67,8 +86,9 @@ impl CrabCheck {
                 args.extend(inc_flags.clone());
                 args.extend(profile_flags.clone());
                 args.extend(user_flags.clone());
+                args.extend(config.build.file_args(src));
 
-                let out = Command::new(&compiler).args(&args).output()?;
+                let out = launcher::command(launcher.as_ref(), &compiler).args(&args).output()?;
                 Ok((src.clone(), out))
             })
             .collect();
@@ -91,4 +111,108 @@ impl CrabCheck {
         crab_status!("Finished", "checking {} in {:.2}s", config.project.name, start.elapsed().as_secs_f64());
         Ok(())
     }
+
+    // Самодостаточность заголовков header-only библиотеки: каждый заголовок
+    // компилируется отдельной единицей трансляции (#include "<header>") с -fsyntax-only.
+    pub(crate) fn check_headers(&self, config: &CrabConfig, profile: &BuildProfile) -> std::io::Result<()> {
+        let compiler   = config.settings.compiler.clone();
+        let header_dir = config.settings.header_dir.clone();
+        let is_c       = config.settings.lang == "c";
+
+        let mut headers: Vec<String> = Vec::new();
+        let exts: &[&str] = if is_c { &["h"] } else { &["h", "hpp", "hh", "hxx"] };
+        for ext in exts {
+            CrabBuildFunc::collect_file_with_extension(Path::new(&header_dir), ext, &mut headers)?;
+        }
+        headers.sort();
+
+        if headers.is_empty() {
+            crab_err!(ErrorKind::NotFound, "No headers found in '{}'", header_dir);
+        }
+
+        let finder = CrabFind::new(".", &profile.dir());
+        let inc_flags = if finder.parsing_include()? { CrabBuild::new().read_include_files_and_fmt(finder.data_dir())? } else { Vec::new() };
+
+        let mut flags = vec![
+            "-fsyntax-only".to_string(),
+            "-x".to_string(),
+            if is_c { "c" } else { "c++" }.to_string(),
+            format!("-I{}", header_dir),
+        ];
+        flags.extend(inc_flags);
+        flags.extend(profile.compile_flags().iter().map(|s| s.to_string()));
+        flags.extend(config.build.compile_args());
+
+        crab_status!("Checking", "{} v{} headers [{}]", config.project.name, config.project.version, profile.dir());
+        crab_log!("INFO", "CHECK", "checking {} headers", headers.len());
+        let start = Instant::now();
+
+        let results: Vec<std::io::Result<(String, std::process::Output)>> = headers
+            .par_iter()
+            .map(|hdr| {
+                let rel = Path::new(hdr).strip_prefix(&header_dir).unwrap_or(Path::new(hdr));
+                let unit = format!("#include \"{}\"\n", rel.display().to_string().replace('\\', "/"));
+
+                let mut child = Command::new(&compiler)
+                    .args(&flags)
+                    .args(config.build.file_args(hdr))
+                    .arg("-")
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .spawn()?;
+                child.stdin.take().expect("stdin is piped").write_all(unit.as_bytes())?;
+
+                Ok((hdr.clone(), child.wait_with_output()?))
+            })
+            .collect();
+
+        let mut had_error = false;
+        for res in results {
+            let (hdr, out) = res?;
+            if !out.status.success() {
+                had_error = true;
+                crab_log!("ERROR", "CHECK", "header {} is not self-contained", hdr);
+                eprint!("{}", String::from_utf8_lossy(&out.stderr));
+            }
+        }
+
+        if had_error {
+            crab_err!(ErrorKind::Other, "header check failed");
+        }
+
+        crab_status!("Finished", "checking {} headers in {:.2}s", headers.len(), start.elapsed().as_secs_f64());
+        Ok(())
+    }
+}
+
+// Исходники без модулей C++20 и исходники, которые объявляют или импортируют модули
+fn split_module_sources(sources: Vec<String>) -> (Vec<String>, Vec<String>) {
+    sources.into_iter().partition(|s| !cxx_modules::uses_modules(s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn module_sources_are_not_checked() {
+        let dir = std::env::temp_dir().join(format!("crab_test_check_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let plain = dir.join("util.cpp");
+        let importer = dir.join("main.cpp");
+        let interface = dir.join("math.cpp");
+        fs::write(&plain, "int twice(int a) { return a * 2; }\n").unwrap();
+        fs::write(&importer, "import math;\nint main() { return add(1, 2); }\n").unwrap();
+        fs::write(&interface, "export module math;\nexport int add(int a, int b) { return a + b; }\n").unwrap();
+
+        let files: Vec<String> = [&importer, &interface, &plain].iter().map(|p| p.display().to_string()).collect();
+        let (checked, skipped) = split_module_sources(files.clone());
+        assert_eq!(checked, vec![files[2].clone()]);
+        assert_eq!(skipped, vec![files[0].clone(), files[1].clone()]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
@@ -1,10 +1,10 @@
 use std::fs::File;
 use std::io::{BufRead, BufReader, Write};
-use std::path::{Path, PathBuf};
+use std::path::Path;
 
 use serde::Serialize;
 
-use crate::config::{load_config, CrabConfig, CONFIG};
+use crate::config::{load_effective_for, CONFIG};
 use crate::find::CrabFind;
 use crate::{crab_err, crab_log, crab_status};
 use super::binary::BuildProfile;
@@ -33,8 +33,8 @@ impl CrabCompDb {
     }
 
     // Чтение каталогов сторонних заголовков (-I) из данных детекта
-    fn third_party_includes(&self) -> std::io::Result<Vec<String>> {
-        let path = PathBuf::from(CONFIG.build_dir).join(CONFIG.data_dir).join(CONFIG.include_file);
+    fn third_party_includes(&self, data: &Path) -> std::io::Result<Vec<String>> {
+        let path = data.join(CONFIG.include_file);
 
         if !path.exists() {
             return Ok(Vec::new());
@@ -56,11 +56,11 @@ impl CrabCompDb {
     pub fn generate(&self, profile: BuildProfile) -> std::io::Result<()> {
         crab_log!("INFO", "COMPDB", "Generating compile_commands.json");
 
-        let config: CrabConfig = load_config(CONFIG.config_file)?;
-        let compiler = config.settings.compiler;
-        let lang = config.settings.lang;
-        let source_dir = config.settings.source_dir;
-        let header_dir = config.settings.header_dir;
+        let config = load_effective_for(&profile.dir())?;
+        let compiler = config.settings.compiler.clone();
+        let lang = config.settings.lang.clone();
+        let source_dir = config.settings.source_dir.clone();
+        let header_dir = config.settings.header_dir.clone();
 
         let cbf = CrabBuildFunc::new();
 
@@ -75,16 +75,18 @@ impl CrabCompDb {
         sources.sort();
 
         // освежаем детект сторонних библиотек, чтобы -I были актуальны
-        CrabFind::new(".").parsing_include()?;
+        let finder = CrabFind::new(".", &profile.dir());
+        finder.parsing_include()?;
 
         // общие для всех файлов флаги: -I заголовков проекта + сторонних + флаги профиля + пользовательские
         let mut common: Vec<String> = Vec::new();
-        if cbf.is_header()? {
+        if cbf.is_header(&config)? {
             common.push(format!("-I{}", header_dir));
         }
-        common.extend(self.third_party_includes()?);
+        common.extend(self.third_party_includes(finder.data_dir())?);
         common.extend(profile.compile_flags().iter().map(|s| s.to_string()));
         common.extend(config.build.compile_args());
+        common.extend(super::pch::header_args(&config.build));
 
         let directory = std::env::current_dir()?.display().to_string();
         let obj_dir = format!("{}/{}/{}", CONFIG.build_dir, profile.dir(), CONFIG.object_dir);
@@ -106,6 +108,7 @@ impl CrabCompDb {
                     obj,
                 ];
                 arguments.extend(common.iter().cloned());
+                arguments.extend(config.build.file_args(src));
 
                 Entry {
                     directory: directory.clone(),
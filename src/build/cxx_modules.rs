@@ -0,0 +1,443 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use rayon::prelude::*;
+use regex::Regex;
+
+use crate::config::cfg::CfgContext;
+use crate::config::{CrabConfig, CONFIG};
+use crate::{crab_err, crab_log};
+use super::helpers::is_assembly;
+
+// Именованные модули C++20 (export module / import). Интерфейсы (.cppm, .ixx и
+// обычные .cpp с export module) компилируются раньше импортирующих их файлов,
+// готовые BMI лежат в <base>/bmi/: у GCC — .gcm через файл -fmodule-mapper,
+// у clang — .pcm, которые находятся по -fprebuilt-module-path.
+
+// Расширения интерфейсных единиц, которые компилятор сам не распознаёт как C++
+const INTERFACE_EXTENSIONS: [&str; 2] = ["cppm", "ixx"];
+
+// Что единица трансляции объявляет и импортирует
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Unit {
+    provides: Option<String>,
+    requires: Vec<String>,
+}
+
+pub(crate) struct ModuleGraph {
+    units: HashMap<String, Unit>,
+    providers: HashMap<String, String>,
+    bmi: PathBuf,
+    clang: bool,
+}
+
+// Файл интерфейса модуля по расширению
+pub(crate) fn is_interface_file(file: &str) -> bool {
+    Path::new(file).extension().and_then(|e| e.to_str()).is_some_and(|e| INTERFACE_EXTENSIONS.contains(&e))
+}
+
+// -x для .cppm/.ixx (перед самим файлом) — иначе GCC примет их за входы компоновщика
+pub(crate) fn language_args(compiler: &str, file: &str) -> Vec<String> {
+    if !is_interface_file(file) {
+        return Vec::new();
+    }
+
+    let lang = if is_clang(compiler) { "c++-module" } else { "c++" };
+    vec!["-x".to_string(), lang.to_string()]
+}
+
+// -MT для -MM: объект интерфейса называется по имени файла целиком (math.cppm.o),
+// чтобы не совпасть с math.o от файла реализации math.cpp
+pub(crate) fn target_args(file: &str) -> Vec<String> {
+    match Path::new(file).file_name().filter(|_| is_interface_file(file)) {
+        Some(name) => vec!["-MT".to_string(), format!("{}.o", name.to_string_lossy())],
+        None => Vec::new(),
+    }
+}
+
+// Объявляет ли файл модуль или импортирует ли что-то — такие файлы нельзя
+// включать в другие через #include (unity-сборка)
+pub(crate) fn uses_modules(file: &str) -> bool {
+    is_interface_file(file) || fs::read_to_string(file).is_ok_and(|c| scan_text(&c) != Unit::default())
+}
+
+// Начинается ли файл с объявления модуля — перед ним нельзя подставлять -include
+pub(crate) fn declares_module(file: &str) -> bool {
+    fs::read_to_string(file).is_ok_and(|c| has_module_declaration(&c))
+}
+
+fn is_clang(compiler: &str) -> bool {
+    CfgContext::new(compiler, None).compiler_family == "clang"
+}
+
+impl ModuleGraph {
+    // Граф модулей по исходникам; None — модули C++20 в проекте не используются.
+    // Сначала быстрый текстовый поиск объявлений; если они есть, уточняем их через
+    // P1689 от компилятора (учитывает #if), а где он недоступен — оставляем текстовые.
+    pub(crate) fn scan(config: &CrabConfig, base: &Path, sources: &[String], args: &[String]) -> std::io::Result<Option<Self>> {
+        if config.settings.lang == "c" {
+            return Ok(None);
+        }
+
+        let mut units = HashMap::new();
+        for s in sources.iter().filter(|s| !is_assembly(s)) {
+            let unit = scan_text(&fs::read_to_string(s)?);
+            if unit != Unit::default() || is_interface_file(s) {
+                units.insert(s.clone(), unit);
+            }
+        }
+
+        if units.is_empty() {
+            return Ok(None);
+        }
+
+        let compiler = &config.settings.compiler;
+        let bmi = base.join(CONFIG.bmi_dir);
+        fs::create_dir_all(&bmi)?;
+
+        let scanned: Vec<(String, Option<Unit>)> = units.keys().cloned().collect::<Vec<_>>().into_par_iter().map(|s| {
+            let unit = scan_p1689(compiler, &bmi, &s, args);
+            (s, unit)
+        }).collect();
+
+        for (s, unit) in scanned {
+            if let Some(unit) = unit {
+                units.insert(s, unit);
+            }
+        }
+
+        let mut providers = HashMap::new();
+        for (s, unit) in &units {
+            if let Some(name) = &unit.provides
+                && let Some(other) = providers.insert(name.clone(), s.clone()) {
+                    crab_err!(ErrorKind::InvalidData, "C++ module {} is declared in both {} and {}", name, other, s);
+                }
+        }
+
+        let graph = Self { units, providers, bmi, clang: is_clang(compiler) };
+        graph.write_mapper()?;
+
+        crab_log!("INFO", "BUILD", "C++ modules: {:?}", graph.providers);
+        Ok(Some(graph))
+    }
+
+    // Флаги для каждой единицы трансляции проекта
+    pub(crate) fn flags(&self) -> Vec<String> {
+        if self.clang {
+            vec![format!("-fprebuilt-module-path={}", self.bmi.display())]
+        } else {
+            vec!["-fmodules-ts".to_string(), format!("-fmodule-mapper={}", self.bmi.join("mapper.txt").display())]
+        }
+    }
+
+    // Дополнительные флаги конкретного файла: clang пишет BMI интерфейса рядом с .o
+    pub(crate) fn unit_args(&self, file: &str) -> Vec<String> {
+        match self.units.get(file).and_then(|u| u.provides.as_ref()) {
+            Some(name) if self.clang => vec![format!("-fmodule-output={}", self.bmi_path(name).display())],
+            _ => Vec::new(),
+        }
+    }
+
+    // Изменённые файлы вместе со всеми, кто (транзитивно) импортирует их модули,
+    // и интерфейсы, чьих BMI нет на диске
+    pub(crate) fn affected(&self, changed: &[String]) -> Vec<String> {
+        let mut result: Vec<String> = changed.to_vec();
+        let mut set: HashSet<String> = changed.iter().cloned().collect();
+
+        for (file, unit) in &self.units {
+            if unit.provides.as_ref().is_some_and(|n| !self.bmi_path(n).exists()) && set.insert(file.clone()) {
+                result.push(file.clone());
+            }
+        }
+
+        let mut i = 0;
+        while i < result.len() {
+            let provided = self.units.get(&result[i]).and_then(|u| u.provides.clone());
+            i += 1;
+
+            let Some(name) = provided else { continue };
+            let mut importers: Vec<&String> = self.units.iter()
+                .filter(|(_, u)| u.requires.contains(&name))
+                .map(|(f, _)| f)
+                .collect();
+            importers.sort();
+
+            for f in importers {
+                if set.insert(f.clone()) {
+                    result.push(f.clone());
+                }
+            }
+        }
+
+        result
+    }
+
+    // Разбиение файлов на волны: каждая собирается после всех модулей, которые импортирует.
+    // Модули, которых нет среди исходников (напр. std), оставляются компилятору.
+    pub(crate) fn waves(&self, files: &[String]) -> std::io::Result<Vec<Vec<String>>> {
+        let mut levels: HashMap<String, usize> = HashMap::new();
+        for f in files {
+            self.level(f, &mut levels, &mut Vec::new())?;
+        }
+
+        let mut waves: Vec<Vec<String>> = Vec::new();
+        for f in files {
+            let level = levels[f];
+            if waves.len() <= level {
+                waves.resize(level + 1, Vec::new());
+            }
+            waves[level].push(f.clone());
+        }
+
+        Ok(waves.into_iter().filter(|w| !w.is_empty()).collect())
+    }
+
+    fn level(&self, file: &str, levels: &mut HashMap<String, usize>, stack: &mut Vec<String>) -> std::io::Result<usize> {
+        if let Some(&level) = levels.get(file) {
+            return Ok(level);
+        }
+
+        let name = |f: &str| self.units.get(f).and_then(|u| u.provides.clone()).unwrap_or_else(|| f.to_string());
+        if let Some(pos) = stack.iter().position(|f| f == file) {
+            let mut cycle: Vec<String> = stack[pos..].iter().map(|f| name(f)).collect();
+            cycle.push(name(file));
+            crab_err!(ErrorKind::InvalidData, "C++ module import cycle: {}", cycle.join(" -> "));
+        }
+
+        stack.push(file.to_string());
+        let mut level = 0;
+        if let Some(unit) = self.units.get(file) {
+            for req in &unit.requires {
+                if let Some(provider) = self.providers.get(req).filter(|p| *p != file) {
+                    level = level.max(self.level(provider, levels, stack)? + 1);
+                } else if !self.providers.contains_key(req) {
+                    crab_log!("INFO", "BUILD", "Module {} imported by {} is not built by crab", req, file);
+                }
+            }
+        }
+        stack.pop();
+
+        levels.insert(file.to_string(), level);
+        Ok(level)
+    }
+
+    // BMI модуля: раздел math:detail -> math-detail
+    fn bmi_path(&self, name: &str) -> PathBuf {
+        let ext = if self.clang { "pcm" } else { "gcm" };
+        self.bmi.join(format!("{}.{}", name.replace(':', "-"), ext))
+    }
+
+    // Файл сопоставления модулей и BMI для GCC (перезаписывается только при изменении)
+    fn write_mapper(&self) -> std::io::Result<()> {
+        if self.clang {
+            return Ok(());
+        }
+
+        let mut names: Vec<&String> = self.providers.keys().collect();
+        names.sort();
+
+        let content: String = names.iter()
+            .map(|n| format!("{} {}\n", n, std::path::absolute(self.bmi_path(n)).unwrap_or_else(|_| self.bmi_path(n)).display()))
+            .collect();
+
+        let path = self.bmi.join("mapper.txt");
+        if fs::read_to_string(&path).ok().as_deref() != Some(content.as_str()) {
+            fs::write(&path, content)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Объявления модуля и импорты в тексте исходника (без препроцессора)
+fn scan_text(content: &str) -> Unit {
+    let decl = Regex::new(r"^(export\s+)?module\s+([A-Za-z_][\w.]*(:[A-Za-z_][\w.]*)?)\s*;").unwrap();
+    let import = Regex::new(r"^(export\s+)?import\s+(:?[A-Za-z_][\w.]*(:[A-Za-z_][\w.]*)?)\s*;").unwrap();
+
+    let mut unit = Unit::default();
+    // модуль, к которому относятся импорты разделов (import :part;)
+    let mut module = String::new();
+    let mut in_comment = false;
+
+    for line in content.lines() {
+        let mut line = line.trim();
+
+        if in_comment {
+            match line.find("*/") {
+                Some(end) => {
+                    line = line[end + 2..].trim();
+                    in_comment = false;
+                }
+                None => continue,
+            }
+        }
+        if let Some(start) = line.find("/*")
+            && !line[start..].contains("*/") {
+                in_comment = true;
+            }
+        if let Some(start) = line.find("//") {
+            line = line[..start].trim();
+        }
+
+        if let Some(caps) = decl.captures(line) {
+            let name = caps[2].to_string();
+            module = name.split(':').next().unwrap_or_default().to_string();
+
+            // module X; — реализация, она зависит от интерфейса X
+            if caps.get(1).is_some() || caps.get(3).is_some() {
+                unit.provides = Some(name);
+            } else {
+                unit.requires.push(name);
+            }
+        } else if let Some(caps) = import.captures(line) {
+            let name = &caps[2];
+            let name = match name.strip_prefix(':') {
+                Some(part) => format!("{}:{}", module, part),
+                None => name.to_string(),
+            };
+
+            if !unit.requires.contains(&name) {
+                unit.requires.push(name);
+            }
+        }
+    }
+
+    unit
+}
+
+// Есть ли в файле объявление модуля (включая глобальный фрагмент module;)
+fn has_module_declaration(content: &str) -> bool {
+    content.lines().map(str::trim).any(|l| l == "module;" || l.starts_with("export module ") || l.starts_with("module "))
+}
+
+// Зависимости модулей в формате P1689 от компилятора: clang-scan-deps у clang,
+// -fdeps-format=p1689r5 у GCC 14+. None — компилятор так не умеет, берём текстовые.
+fn scan_p1689(compiler: &str, bmi: &Path, file: &str, args: &[String]) -> Option<Unit> {
+    let name = Path::new(file).file_name()?.to_string_lossy().to_string();
+    let ddi = bmi.join(format!("{}.ddi", name));
+    let object = format!("{}.o", name);
+
+    let output = if is_clang(compiler) {
+        // clang++-17 -> clang-scan-deps-17
+        let scanner = if compiler.contains("clang++") {
+            compiler.replacen("clang++", "clang-scan-deps", 1)
+        } else {
+            compiler.replacen("clang", "clang-scan-deps", 1)
+        };
+
+        let out = Command::new(scanner)
+            .arg("-format=p1689")
+            .arg("--")
+            .arg(compiler)
+            .args(args)
+            .args(language_args(compiler, file))
+            .arg("-c")
+            .arg(file)
+            .arg("-o")
+            .arg(&object)
+            .output()
+            .ok()?;
+        out.status.success().then_some(out.stdout)?
+    } else {
+        let out = Command::new(compiler)
+            .args(args)
+            .arg("-fmodules-ts")
+            .arg("-E")
+            .args(language_args(compiler, file))
+            .arg(file)
+            .arg("-MD")
+            .arg("-MF")
+            .arg(bmi.join(format!("{}.ddi.d", name)))
+            .arg("-MT")
+            .arg(&object)
+            .arg("-fdeps-format=p1689r5")
+            .arg(format!("-fdeps-file={}", ddi.display()))
+            .arg(format!("-fdeps-target={}", object))
+            .arg("-o")
+            .arg(bmi.join(format!("{}.i", name)))
+            .output()
+            .ok()?;
+        if !out.status.success() {
+            return None;
+        }
+        fs::read(&ddi).ok()?
+    };
+
+    parse_p1689(&String::from_utf8_lossy(&output))
+}
+
+// {"rules": [{"provides": [{"logical-name": "m"}], "requires": [{"logical-name": "n"}]}]}
+fn parse_p1689(text: &str) -> Option<Unit> {
+    let json: serde_json::Value = serde_json::from_str(text).ok()?;
+    let rule = json.get("rules")?.as_array()?.first()?;
+
+    let names = |key: &str| -> Vec<String> {
+        rule.get(key)
+            .and_then(|v| v.as_array())
+            .map(|a| a.iter().filter_map(|m| m.get("logical-name")?.as_str().map(str::to_string)).collect())
+            .unwrap_or_default()
+    };
+
+    Some(Unit { provides: names("provides").into_iter().next(), requires: names("requires") })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(units: &[(&str, Option<&str>, &[&str])]) -> ModuleGraph {
+        let units: HashMap<String, Unit> = units.iter().map(|(f, p, r)| {
+            (f.to_string(), Unit { provides: p.map(str::to_string), requires: r.iter().map(|s| s.to_string()).collect() })
+        }).collect();
+        let providers = units.iter().filter_map(|(f, u)| Some((u.provides.clone()?, f.clone()))).collect();
+        ModuleGraph { units, providers, bmi: PathBuf::from("/nonexistent/bmi"), clang: false }
+    }
+
+    #[test]
+    fn scan_text_reads_declarations_and_imports() {
+        let unit = scan_text("module;\n#include <cstdio>\nexport module math;\n// import fake;\nexport import :detail;\nimport std;\nimport <vector>;\n");
+        assert_eq!(unit.provides.as_deref(), Some("math"));
+        assert_eq!(unit.requires, vec!["math:detail", "std"]);
+
+        let unit = scan_text("module math;\nimport io;\n");
+        assert_eq!(unit.provides, None);
+        assert_eq!(unit.requires, vec!["math", "io"]);
+
+        assert_eq!(scan_text("/*\nimport hidden;\n*/\nint main() {}\n"), Unit::default());
+    }
+
+    #[test]
+    fn parse_p1689_takes_logical_names() {
+        let unit = parse_p1689(r#"{"revision":0,"rules":[{"primary-output":"a.o","provides":[{"logical-name":"math","is-interface":true}],"requires":[{"logical-name":"math:detail"}]}],"version":1}"#).unwrap();
+        assert_eq!(unit.provides.as_deref(), Some("math"));
+        assert_eq!(unit.requires, vec!["math:detail"]);
+    }
+
+    #[test]
+    fn waves_put_interfaces_before_importers() {
+        let g = graph(&[
+            ("main.cpp", None, &["math", "std"]),
+            ("math.cppm", Some("math"), &["math:detail"]),
+            ("detail.cppm", Some("math:detail"), &[]),
+            ("util.cpp", None, &[]),
+        ]);
+        let files: Vec<String> = ["main.cpp", "math.cppm", "detail.cppm", "util.cpp"].iter().map(|s| s.to_string()).collect();
+        let waves = g.waves(&files).unwrap();
+        assert_eq!(waves, vec![vec!["detail.cppm", "util.cpp"], vec!["math.cppm"], vec!["main.cpp"]]);
+
+        // правка раздела пересобирает интерфейс и всех, кто его импортирует
+        let mut affected = g.affected(&["detail.cppm".to_string()]);
+        affected.sort();
+        assert_eq!(affected, vec!["detail.cppm", "main.cpp", "math.cppm"]);
+    }
+
+    #[test]
+    fn waves_report_import_cycles() {
+        let g = graph(&[("a.cppm", Some("a"), &["b"]), ("b.cppm", Some("b"), &["a"])]);
+        let err = g.waves(&["a.cppm".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("a -> b -> a"));
+    }
+}
@@ -2,6 +2,7 @@ use std::fs::{self, OpenOptions};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Output, Stdio};
+use std::sync::LazyLock;
 use std::time::UNIX_EPOCH;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use regex::Regex;
@@ -9,8 +10,12 @@ use regex::Regex;
 use chrono::DateTime;
 use rayon::prelude::*;
 
-use crate::config::{load_config, save_config, Changed, CrabConfig, CONFIG};
+use crate::config::{add_files, load_config, load_effective, load_effective_for, save_config, Changed, CrabConfig, CONFIG};
 use crate::{crab_err, crab_print, crab_log};
+use super::binary::CrabBuild;
+use super::cxx_modules;
+use super::launcher::Launcher;
+use super::pch::Pch;
 use std::io::ErrorKind;
 
 pub struct CrabBuildFunc;
@@ -22,7 +27,7 @@ impl CrabBuildFunc {
 
     // Проверка на наличие компилятора перед сборкой
     pub(crate) fn is_compiler(&self) -> std::io::Result<()>  {
-        let config: CrabConfig = load_config(CONFIG.config_file)?;
+        let config = load_effective()?;
         let compiler = config.settings.compiler;
 
         crab_log!("INFO", "BUILD" ,"Checking the compiler: {}", compiler);
@@ -32,9 +37,11 @@ impl CrabBuildFunc {
             crab_err!(ErrorKind::NotFound, "The compiler is missing: {}", compiler);
         } else {
             crab_log!("INFO", "BUILD", "Health check of the {} compiler", compiler);
-            let status = Command::new(&compiler).arg("--version").stdout(Stdio::null()).stderr(Stdio::null()).status()?;
+            // Не запустился (нет в PATH) — та же ошибка, что и при ненулевом коде
+            let ok = Command::new(&compiler).arg("--version").stdout(Stdio::null()).stderr(Stdio::null()).status()
+                .is_ok_and(|s| s.success());
 
-            if !status.success() {
+            if !ok {
                 crab_log!("ERROR", "BUILD", "Incorrect compiler name or missing compiler: {}", compiler);
                 crab_err!(ErrorKind::Other, "Incorrect compiler name or missing compiler: {}", compiler);
             }
@@ -97,35 +104,34 @@ impl CrabBuildFunc {
         }
     }
 
-    // Создания дебаг или релиз директорий со всем содержимым
+    // Создание каталогов профиля (debug, release, san-*) со всем содержимым
     pub(crate) fn create_build_dir(&self, flag: &str) -> std::io::Result<()> {
-        let object_dir = if flag == "debug" {
-            PathBuf::from(CONFIG.build_dir).join(CONFIG.debug_dir).join(CONFIG.object_dir)
-        } else {
-            PathBuf::from(CONFIG.build_dir).join(CONFIG.release_dir).join(CONFIG.object_dir)
-        };
+        self.create_target_dir(&PathBuf::from(CONFIG.build_dir).join(flag), true)
+    }
 
-        let binary_dir = if flag == "debug" {
-            PathBuf::from(CONFIG.build_dir).join(CONFIG.debug_dir).join(CONFIG.binary_dir)
-        } else {
-            PathBuf::from(CONFIG.build_dir).join(CONFIG.release_dir).join(CONFIG.binary_dir)
-        };
+    // Создание директорий для модуля
+    pub(crate) fn create_module_dir(&self, flag: &str, name: &str) -> std::io::Result<()> {
+        self.create_target_dir(&PathBuf::from(CONFIG.build_dir).join(CONFIG.module_dir).join(name).join(flag), true)
+    }
 
-        let dependencies_file = if flag == "debug" {
-            PathBuf::from(CONFIG.build_dir).join(CONFIG.debug_dir).join(CONFIG.dependencies)
-        } else {
-            PathBuf::from(CONFIG.build_dir).join(CONFIG.release_dir).join(CONFIG.dependencies)
-        };
+    // Каталог цели: obj/, bin/ (если нужен) и пустой файл зависимостей
+    pub(crate) fn create_target_dir(&self, base: &Path, with_bin: bool) -> std::io::Result<()> {
+        let object_dir = base.join(CONFIG.object_dir);
+        let binary_dir = base.join(CONFIG.binary_dir);
+        let dependencies_file = base.join(CONFIG.dependencies);
 
-        crab_log!("INFO", "BUILD", "Checking the existence of a directory for object files: {}", dependencies_file.display());
+        crab_log!("INFO", "BUILD", "Checking the existence of a directory for object files: {}", object_dir.display());
         if !object_dir.exists() {
-            crab_log!("INFO", "BUILD", "The directory does not exist, create: {}", dependencies_file.display());
+            crab_log!("INFO", "BUILD", "The directory does not exist, create: {}", object_dir.display());
             fs::create_dir_all(object_dir)?;
         }
-        crab_log!("INFO", "BUILD", "Checking the existence of a directory for binary files: {}", binary_dir.display());
-        if !binary_dir.exists() {
-            crab_log!("INFO", "BUILD", "The directory does not exist, create: {}", binary_dir.display());
-            fs::create_dir_all(binary_dir)?;
+
+        if with_bin {
+            crab_log!("INFO", "BUILD", "Checking the existence of a directory for binary files: {}", binary_dir.display());
+            if !binary_dir.exists() {
+                crab_log!("INFO", "BUILD", "The directory does not exist, create: {}", binary_dir.display());
+                fs::create_dir_all(binary_dir)?;
+            }
         }
 
         crab_log!("INFO", "BUILD", "Checking for the existence of a dependency file: {}", dependencies_file.display());
@@ -137,72 +143,42 @@ impl CrabBuildFunc {
         Ok(())
     }
 
-    // Создание директорий для модуля
-    pub(crate) fn create_module_dir(&self, flag: &str, name: &str) -> std::io::Result<()> {
-        let object_dir = if flag == "debug" {
-            PathBuf::from(CONFIG.build_dir).join(CONFIG.module_dir).join(name).join(CONFIG.debug_dir).join(CONFIG.object_dir)
-        } else {
-            PathBuf::from(CONFIG.build_dir).join(CONFIG.module_dir).join(name).join(CONFIG.release_dir).join(CONFIG.object_dir)
-        };
-
-        let binary_dir = if flag == "debug" {
-            PathBuf::from(CONFIG.build_dir).join(CONFIG.module_dir).join(name).join(CONFIG.debug_dir).join(CONFIG.binary_dir)
-        } else {
-            PathBuf::from(CONFIG.build_dir).join(CONFIG.module_dir).join(name).join(CONFIG.release_dir).join(CONFIG.binary_dir)
-        };
+    // Запись зависимостей (-MM) в path_dep. Заголовки ищутся с теми же -I,
+    // что и при компиляции: include/, [build], флаги файла и найденные сторонние библиотеки.
+    pub(crate) fn write_dependencies(&self, config: &CrabConfig, path_dep: &Path, cpp: &[String], find: Option<&Path>) -> std::io::Result<()> {
+        crab_log!("INFO", "BUILD","Write dependencies");
+        let compiler = config.settings.compiler.clone();
 
-        let dependencies_file = if flag == "debug" {
-            PathBuf::from(CONFIG.build_dir).join(CONFIG.module_dir).join(name).join(CONFIG.debug_dir).join(CONFIG.dependencies)
-        } else {
-            PathBuf::from(CONFIG.build_dir).join(CONFIG.module_dir).join(name).join(CONFIG.release_dir).join(CONFIG.dependencies)
-        };
+        // отсутствующая обёртка компиляции — ошибка до того, как get_changed_files запишет состояние
+        Launcher::resolve(&config.build)?;
 
-        crab_log!("INFO", "BUILD", "Module: Checking the existence of a directory for object files: {}", dependencies_file.display());
-        if !object_dir.exists() {
-            crab_log!("INFO", "BUILD", "Module: The directory does not exist, create: {}", dependencies_file.display());
-            fs::create_dir_all(object_dir)?;
-        }
-        crab_log!("INFO", "BUILD", "Module: Checking the existence of a directory for binary files: {}", binary_dir.display());
-        if !binary_dir.exists() {
-            crab_log!("INFO", "BUILD", "Module: The directory does not exist, create: {}", binary_dir.display());
-            fs::create_dir_all(binary_dir)?;
+        let mut include_args = Vec::new();
+        if self.is_header(config)? {
+            include_args.push(format!("-I{}", config.settings.header_dir));
         }
-
-        crab_log!("INFO", "BUILD", "Module: Checking for the existence of a dependency file: {}", dependencies_file.display());
-        if !dependencies_file.exists() {
-            crab_log!("INFO", "BUILD", "Module: The file does not exist, create: {}", dependencies_file.display());
-            fs::File::create(dependencies_file)?;
+        include_args.extend(config.build.compile_args());
+        if let Some(data) = find {
+            include_args.extend(CrabBuild::new().read_include_files_and_fmt(data)?);
         }
-
-        Ok(())
-    }
-
-    // Запись зависимостей
-    pub(crate) fn write_dependencies(&self, flag: &str, cpp: &[String]) -> std::io::Result<()> {
-        crab_log!("INFO", "BUILD","Write dependencies");
-        let config: CrabConfig = load_config(CONFIG.config_file)?;
-        let compiler = config.settings.compiler;
-
-        let path_to_dependencies_file = if flag == "debug" {
-            PathBuf::from(CONFIG.build_dir).join(CONFIG.debug_dir).join(CONFIG.dependencies)
-        } else if flag == "release" {
-            PathBuf::from(CONFIG.build_dir).join(CONFIG.release_dir).join(CONFIG.dependencies)
-        } else {
-            // static / dynamic — общий каталог библиотеки
-            PathBuf::from(CONFIG.build_dir).join(CONFIG.library_dir).join(CONFIG.dependencies)
-        };
+        let pch_args = Pch::prepare(config, path_dep.parent().unwrap_or(Path::new(".")))?.map(|p| p.include_args()).unwrap_or_default();
 
         // Перезаписываем файл с нуля, чтобы зависимости не накапливались между сборками
-        let mut file = OpenOptions::new().write(true).create(true).truncate(true).open(&path_to_dependencies_file)?;
+        let mut file = OpenOptions::new().write(true).create(true).truncate(true).open(path_dep)?;
 
         crab_log!("INFO", "BUILD", "Collecting dependencies");
         let result: Vec<std::io::Result<Output>> = cpp.par_iter().map(|c| {
-            Command::new(&compiler).arg("-MM").arg(c).output()
+            // .cppm/.ixx и ассемблер — со своим именем объекта; в ассемблер и единицы
+            // с объявлением модуля pch не подставляется
+            let asm = is_assembly(c);
+            let target = if asm { vec!["-MT".to_string(), assembly_object(c)] } else { cxx_modules::target_args(c) };
+            let pch = if asm || cxx_modules::declares_module(c) { &[][..] } else { &pch_args[..] };
+            Command::new(&compiler).arg("-MM").args(cxx_modules::language_args(&compiler, c)).arg(c).args(target)
+                .args(pch).args(&include_args).args(config.build.file_args(c)).output()
         }).collect();
 
-        crab_log!("INFO", "BUILD", "Writing dependencies to a file: {}", path_to_dependencies_file.display());
+        crab_log!("INFO", "BUILD", "Writing dependencies to a file: {}", path_dep.display());
 
-        for (dep, _) in result.into_iter().zip(cpp.iter()) {
+        for (dep, c) in result.into_iter().zip(cpp.iter()) {
             let dep = dep?;
 
             if !dep.status.success() {
@@ -212,43 +188,11 @@ impl CrabBuildFunc {
             }
 
             file.write_all(&dep.stdout)?;
-        }
 
-        Ok(())
-    }
-
-    // Запись зависимостей для модуля
-    pub(crate) fn write_dependencies_module(&self, name: &str, flag: &str, cpp: &[String]) -> std::io::Result<()> {
-        crab_log!("INFO", "BUILD","Module: Write dependencies");
-        let config: CrabConfig = load_config(CONFIG.config_file)?;
-        let compiler = config.settings.compiler;
-
-        let path_to_dependencies_file = if flag == "debug" {
-            PathBuf::from(CONFIG.build_dir).join(CONFIG.module_dir).join(name).join(CONFIG.debug_dir).join(CONFIG.dependencies)
-        } else {
-            PathBuf::from(CONFIG.build_dir).join(CONFIG.module_dir).join(name).join(CONFIG.release_dir).join(CONFIG.dependencies)
-        };
-
-        // Перезаписываем файл с нуля, чтобы зависимости не накапливались между сборками
-        let mut file = OpenOptions::new().write(true).create(true).truncate(true).open(&path_to_dependencies_file)?;
-
-        crab_log!("INFO", "BUILD", "Module: Collecting dependencies");
-        let result: Vec<std::io::Result<Output>> = cpp.par_iter().map(|c| {
-            Command::new(&compiler).arg("-MM").arg(c).output()
-        }).collect();
-
-        crab_log!("INFO", "BUILD", "Module: Writing dependencies to a file: {}", path_to_dependencies_file.display());
-
-        for (dep, _) in result.into_iter().zip(cpp.iter()) {
-            let dep = dep?;
-
-            if !dep.status.success() {
-                let stderr = String::from_utf8_lossy(&dep.stderr).to_string();
-                crab_log!("ERROR", "BUILD", "Module: Error when collecting dependencies: {}", stderr);
-                crab_err!(ErrorKind::Other, "Error when collecting dependencies: {}", stderr);
+            // -MM ничего не выводит для .s (без препроцессора) — зависимость только от самого файла
+            if dep.stdout.is_empty() && is_assembly(c) {
+                writeln!(file, "{}: {}", assembly_object(c), c)?;
             }
-
-            file.write_all(&dep.stdout)?;
         }
 
         Ok(())
@@ -274,10 +218,8 @@ impl CrabBuildFunc {
         }
 
     // Функции для проверки существование папки header и файлов в ней
-    pub(crate) fn is_header(&self) -> std::io::Result<bool> {
-
-        let config: CrabConfig = load_config(CONFIG.config_file)?;
-        let header_dir = config.settings.header_dir;
+    pub(crate) fn is_header(&self, config: &CrabConfig) -> std::io::Result<bool> {
+        let header_dir = &config.settings.header_dir;
         crab_log!("INFO", "BUILD", "Checking the existence of a directory: {}", header_dir);
 
         let path = Path::new(header_dir.as_str());
@@ -303,21 +245,43 @@ impl CrabBuildFunc {
         Ok(parse_dependencies_content(&content, lang))
     }
 
-    // Получение списка исходников, которые нужно пересобрать (с учётом изменений заголовков)
-    pub(crate) fn get_changed_files(&self, path_to_obj_data: &Path, path_dep: &Path, cpp: &[String], lang: &str) -> std::io::Result<Vec<String>> {
+    // Результат свежее всех исходников и их заголовков из файла зависимостей;
+    // исходник, которого нет в файле зависимостей, ещё не собирался
+    pub(crate) fn is_output_fresh(&self, output: &Path, path_dep: &Path, sources: &[String], lang: &str) -> bool {
+        let Ok(built) = fs::metadata(output).and_then(|m| m.modified()) else {
+            return false;
+        };
+        let Ok(deps) = self.parse_dependencies(path_dep, lang) else {
+            return false;
+        };
+
+        sources.iter().all(|s| deps.get(s).is_some_and(|files| files.iter().all(|f| {
+            fs::metadata(f).and_then(|m| m.modified()).is_ok_and(|t| t <= built)
+        })))
+    }
+
+    // Получение списка исходников, которые нужно пересобрать (с учётом изменений заголовков).
+    // signature — компилятор и флаги сборки; если он отличается от прошлого, пересобирается всё.
+    pub(crate) fn get_changed_files(&self, path_to_obj_data: &Path, path_dep: &Path, cpp: &[String], lang: &str, signature: &str) -> std::io::Result<Vec<String>> {
         crab_log!("INFO", "BUILD", "Checking for file modification");
 
         let deps_map = self.parse_dependencies(path_dep, lang)?;
 
         // Снимок прошлого состояния (read-only для сравнения)
-        let old: HashMap<String, String> = if path_to_obj_data.exists() {
-            load_config::<Changed>(path_to_obj_data.display().to_string().as_str())?.files
+        let (old, old_signature): (HashMap<String, String>, String) = if path_to_obj_data.exists() {
+            let prev = load_config::<Changed>(path_to_obj_data.display().to_string().as_str())?;
+            (prev.files, prev.signature)
         } else {
             crab_log!("INFO", "BUILD", "There is no file for tracking modifications, create: {}", path_to_obj_data.display());
             fs::File::create(path_to_obj_data)?;
-            HashMap::new()
+            (HashMap::new(), String::new())
         };
 
+        let flags_changed = old_signature != signature;
+        if flags_changed {
+            crab_log!("INFO", "BUILD", "Compiler or flags changed: {:?} -> {:?}", old_signature, signature);
+        }
+
         let mut changed = Vec::new();
         let mut new_state: HashMap<String, String> = HashMap::new();
 
@@ -343,12 +307,12 @@ impl CrabBuildFunc {
                 new_state.insert(p.clone(), new_time);
             }
 
-            if need_rebuild {
+            if need_rebuild || flags_changed {
                 changed.push(c.clone());
             }
         }
 
-        let change = Changed { files: new_state };
+        let change = Changed { files: new_state, signature: signature.to_string() };
         save_config(&change, path_to_obj_data.display().to_string().as_str())?;
         crab_log!("INFO", "BUILD", "Modified files: {:?}", changed);
         Ok(changed)
@@ -356,13 +320,15 @@ impl CrabBuildFunc {
 
     // Удаление осиротевших .o из obj-каталога: объектов, для которых больше нет
     // цели в текущем .d файле (исходник удалён). Иначе линковка тянула бы их.
-    pub(crate) fn prune_orphan_objects(&self, path_dep: &Path, path_obj: &Path) -> std::io::Result<()> {
+    // Возвращает true, если что-то было удалено.
+    pub(crate) fn prune_orphan_objects(&self, path_dep: &Path, path_obj: &Path) -> std::io::Result<bool> {
         if !path_dep.exists() || !path_obj.exists() {
-            return Ok(());
+            return Ok(false);
         }
 
         let content = fs::read_to_string(path_dep)?;
         let valid = dependency_object_names(&content);
+        let mut removed = false;
 
         for entry in fs::read_dir(path_obj)? {
             let entry = entry?;
@@ -376,16 +342,17 @@ impl CrabBuildFunc {
                 && !valid.contains(name) {
                     crab_print!(red, "Removing orphan object: {}", path.display());
                     crab_log!("INFO", "BUILD", "Removing orphan object: {}", path.display());
-                    let _ = fs::remove_file(&path);
+                    removed |= fs::remove_file(&path).is_ok();
                 }
         }
 
-        Ok(())
+        Ok(removed)
     }
 
-    // Проверка игнорируемых файлов
-    pub(crate) fn check_ignore_files(&self, cpp: &mut Vec<String>) -> std::io::Result<()> {
-        let config: CrabConfig = load_config(CONFIG.config_file)?;
+    // Проверка игнорируемых файлов. [files] читается заново после write_file_in_config,
+    // с учётом extends — флаг выключения может прийти из базового конфига.
+    pub(crate) fn check_ignore_files(&self, flag: &str, cpp: &mut Vec<String>) -> std::io::Result<()> {
+        let config = load_effective_for(flag)?;
         let file_list = config.files;
 
         crab_log!("INFO", "BUILD", "Checking ignored files");
@@ -422,22 +389,12 @@ impl CrabBuildFunc {
         }
 
         crab_log!("INFO", "BUILD", "Writing files to the configuration");
-        let mut config: CrabConfig = load_config(CONFIG.config_file)?;
-        let mut files = config.files;
 
-        for c in cpp {
-            if files.contains_key(c) {
-                continue;
-            }
-
-            files.insert(c.to_string(), "on".to_string());
+        // без новых файлов config.toml не перезаписывается (модули собираются параллельно)
+        if add_files(CONFIG.config_file, cpp)? {
+            crab_log!("INFO", "BUILD", "Written files to the configuration: {:?}", cpp);
         }
 
-        config.files = files;
-        save_config(&config, CONFIG.config_file)?;
-
-        crab_log!("INFO", "BUILD", "Written files to the configuration: {:?}", cpp);
-
         Ok(())
     }
 
@@ -450,18 +407,21 @@ impl CrabBuildFunc {
 
         let data_init = clean_text.split(':').nth(1).unwrap_or("");
 
-        let data_vec: Vec<&str> = if lang == "c" {
-            data_init.split_whitespace().filter(|s| !s.is_empty() && s.ends_with(".c")).collect()
-        } else {
-            data_init.split_whitespace().filter(|s| !s.is_empty() && s.ends_with(".cpp")).collect()
-        };
-
-        let data = data_vec.join("");
+        // первым идёт сам исходник; остальные .cpp — включённые в него (unity-сборка)
+        let data = data_init.split_whitespace().find(|s| is_source(s, lang)).unwrap_or("").to_string();
 
         Ok([o_str, data])
 
     }
 
+    // Сбор исходников языка со всеми его расширениями (см. source_extensions)
+    pub(crate) fn collect_sources(dir: &Path, lang: &str, files: &mut Vec<String>) -> std::io::Result<()> {
+        for ext in source_extensions(lang) {
+            Self::collect_file_with_extension(dir, ext, files)?;
+        }
+        Ok(())
+    }
+
     // Сбор всех файлов с определённым расширением
     pub(crate) fn collect_file_with_extension(dir: &Path, extension: &str, files: &mut Vec<String>) -> std::io::Result<()> {
         if dir.is_dir() {
@@ -487,12 +447,41 @@ impl CrabBuildFunc {
 
 }
 
+// Расширения исходников языка; у C++ — ещё интерфейсы модулей C++20, у обоих — ассемблер
+pub(crate) fn source_extensions(lang: &str) -> &'static [&'static str] {
+    if lang == "c" { &["c", "S", "s"] } else { &["cpp", "cppm", "ixx", "S", "s"] }
+}
+
+// Ассемблер: .S проходит через препроцессор (и -MM), .s — нет
+pub(crate) fn is_assembly(path: &str) -> bool {
+    Path::new(path).extension().is_some_and(|e| e == "S" || e == "s")
+}
+
+static MAIN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?m)^\s*(?:int|auto)\s+main\s*\(").unwrap());
+
+// Точка входа: main.c/main.cpp или файл, в котором определена функция main.
+// Её объект не линкуется с тестами (у теста свой main), не попадает в unity-пачки,
+// а по её наличию crab install --prefix отличает программу от библиотеки.
+pub(crate) fn is_entry_point(file: &str) -> bool {
+    Path::new(file).file_stem().is_some_and(|s| s.eq_ignore_ascii_case("main"))
+        || fs::read_to_string(file).is_ok_and(|c| MAIN.is_match(&c))
+}
+
+// Объект ассемблерного файла называется по имени целиком (memcpy.S.o),
+// чтобы не совпасть с объектом одноимённого .c/.cpp
+fn assembly_object(file: &str) -> String {
+    format!("{}.o", Path::new(file).file_name().unwrap_or_default().to_string_lossy())
+}
+
+// Исходник ли это языка lang (по расширению)
+pub(crate) fn is_source(path: &str, lang: &str) -> bool {
+    Path::new(path).extension().and_then(|e| e.to_str()).is_some_and(|e| source_extensions(lang).contains(&e))
+}
+
 // Чистый парсер содержимого .d файла (вынесен из parse_dependencies для тестируемости):
 // исходник -> [сам исходник + все его заголовки]
 fn parse_dependencies_content(content: &str, lang: &str) -> HashMap<String, Vec<String>> {
     let mut map: HashMap<String, Vec<String>> = HashMap::new();
-    let ext = if lang == "c" { ".c" } else { ".cpp" };
-
     // Склеиваем переносы строк вида "... \<newline>" в одну запись
     let joined = content.replace("\\\r\n", " ").replace("\\\n", " ");
 
@@ -510,7 +499,7 @@ fn parse_dependencies_content(content: &str, lang: &str) -> HashMap<String, Vec<
         let prereqs: Vec<String> = rhs.split_whitespace().map(|s| s.to_string()).collect();
 
         // Исходник — это зависимость с нужным расширением, по ней и индексируем
-        if let Some(src) = prereqs.iter().find(|p| p.ends_with(ext)) {
+        if let Some(src) = prereqs.iter().find(|p| is_source(p, lang)) {
             map.entry(src.clone()).or_default().extend(prereqs.iter().cloned());
         }
     }
@@ -551,6 +540,41 @@ mod tests {
         assert_eq!(res[1], "src/main.c");
     }
 
+    #[test]
+    fn split_dep_takes_first_source_of_unity_unit() {
+        let res = CrabBuildFunc::new()
+            .split_dep("unity_0.o: crb/debug/unity/unity_0.cpp /p/src/a.cpp /p/src/b.cpp", "c++")
+            .unwrap();
+        assert_eq!(res[1], "crb/debug/unity/unity_0.cpp");
+    }
+
+    #[test]
+    fn assembly_sources_are_tracked_for_both_languages() {
+        assert!(is_source("src/memcpy.S", "c") && is_source("src/memcpy.s", "c++"));
+        assert!(is_source("src/math.cppm", "c++") && !is_source("src/math.cppm", "c"));
+        assert_eq!(assembly_object("src/arch/memcpy.S"), "memcpy.S.o");
+
+        let res = CrabBuildFunc::new().split_dep("memcpy.S.o: src/memcpy.S include/defs.h", "c").unwrap();
+        assert_eq!(res, ["memcpy.S.o".to_string(), "src/memcpy.S".to_string()]);
+    }
+
+    #[test]
+    fn entry_point_is_main_file_or_main_definition() {
+        let dir = std::env::temp_dir().join(format!("crab_test_entry_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let cli = dir.join("cli.cpp");
+        let util = dir.join("util.cpp");
+        fs::write(&cli, "#include <cstdio>\n\nint main(int argc, char** argv) {\n    return 0;\n}\n").unwrap();
+        fs::write(&util, "// int main() is in cli.cpp\nint domain(int x) { return x; }\n").unwrap();
+
+        assert!(is_entry_point("src/main.cpp") && is_entry_point("src/Main.c"));
+        assert!(is_entry_point(&cli.display().to_string()));
+        assert!(!is_entry_point(&util.display().to_string()));
+        assert!(!is_entry_point(&dir.join("missing.cpp").display().to_string()));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn split_dep_non_target_line_has_no_object() {
         // строка-продолжение без ".o:" не должна распознаваться как объект
@@ -636,20 +660,48 @@ mod tests {
 
         // первый запуск: файла состояния нет -> исходник считается изменённым
         let changed = cbf
-            .get_changed_files(&obj_data, &dep, std::slice::from_ref(&src_s), "c++")
+            .get_changed_files(&obj_data, &dep, std::slice::from_ref(&src_s), "c++", "g++")
             .unwrap();
         assert_eq!(changed, vec![src_s.clone()]);
 
         // повторный запуск без изменения mtime -> пересборка не нужна
         set_mtime(&src, 1_000_000_000);
         let changed = cbf
-            .get_changed_files(&obj_data, &dep, std::slice::from_ref(&src_s), "c++")
+            .get_changed_files(&obj_data, &dep, std::slice::from_ref(&src_s), "c++", "g++")
             .unwrap();
         assert!(changed.is_empty(), "expected no changes, got {:?}", changed);
 
         let _ = fs::remove_dir_all(&dir);
     }
 
+    #[test]
+    fn get_changed_rebuilds_everything_when_signature_changes() {
+        let dir = temp_dir("changed_sig");
+        let src = dir.join("a.cpp");
+        fs::write(&src, "int main(){}\n").unwrap();
+        let src_s = src.to_string_lossy().to_string();
+
+        let dep = dir.join("dep.d");
+        fs::write(&dep, format!("a.o: {}\n", src_s)).unwrap();
+        set_mtime(&src, 1_000_000_000);
+
+        let obj_data = dir.join("obj_data.crb");
+        let cbf = CrabBuildFunc::new();
+
+        let _ = cbf
+            .get_changed_files(&obj_data, &dep, std::slice::from_ref(&src_s), "c++", "g++ -O0")
+            .unwrap();
+
+        // исходник не менялся, но сменились флаги (например, CXXFLAGS) -> пересборка
+        set_mtime(&src, 1_000_000_000);
+        let changed = cbf
+            .get_changed_files(&obj_data, &dep, std::slice::from_ref(&src_s), "c++", "g++ -O3")
+            .unwrap();
+        assert_eq!(changed, vec![src_s.clone()]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn get_changed_detects_header_change() {
         let dir = temp_dir("changed_b");
@@ -671,21 +723,21 @@ mod tests {
 
         // первый запуск (запоминаем состояние)
         let _ = cbf
-            .get_changed_files(&obj_data, &dep, std::slice::from_ref(&src_s), "c++")
+            .get_changed_files(&obj_data, &dep, std::slice::from_ref(&src_s), "c++", "g++")
             .unwrap();
 
         // ничего не менялось
         set_mtime(&src, 1_000_000_000);
         set_mtime(&hdr, 1_000_000_000);
         let changed = cbf
-            .get_changed_files(&obj_data, &dep, std::slice::from_ref(&src_s), "c++")
+            .get_changed_files(&obj_data, &dep, std::slice::from_ref(&src_s), "c++", "g++")
             .unwrap();
         assert!(changed.is_empty(), "expected no changes, got {:?}", changed);
 
         // изменился только заголовок -> исходник должен пересобраться
         set_mtime(&hdr, 2_000_000_000);
         let changed = cbf
-            .get_changed_files(&obj_data, &dep, std::slice::from_ref(&src_s), "c++")
+            .get_changed_files(&obj_data, &dep, std::slice::from_ref(&src_s), "c++", "g++")
             .unwrap();
         assert_eq!(changed, vec![src_s.clone()]);
 
@@ -0,0 +1,148 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::Build;
+use crate::{crab_err, crab_status};
+
+// Обёртка компиляции ([build] launcher или CRAB_LAUNCHER), напр. ccache или sccache:
+// каждая компиляция запускается как <launcher> <compiler> ..., линковка — напрямую.
+pub(crate) struct Launcher {
+    program: PathBuf,
+    args: Vec<String>,
+}
+
+// Счётчики кэша обёртки (попадания и промахи)
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct CacheStats {
+    hits: u64,
+    misses: u64,
+}
+
+impl Launcher {
+    // None — обёртка не задана; если её нет в PATH — понятная ошибка, а не сбой каждой компиляции
+    pub(crate) fn resolve(build: &Build) -> std::io::Result<Option<Self>> {
+        let mut parts = build.launcher.split_whitespace().map(str::to_string);
+        let Some(name) = parts.next() else {
+            return Ok(None);
+        };
+
+        let Some(program) = find_program(&name) else {
+            crab_err!(ErrorKind::NotFound, "Compiler launcher not found: {} (install it or change [build] launcher / CRAB_LAUNCHER)", name);
+        };
+
+        Ok(Some(Self { program, args: parts.collect() }))
+    }
+
+    // Текущие счётчики кэша; None — обёртка их не сообщает
+    pub(crate) fn stats(&self) -> Option<CacheStats> {
+        match self.name().as_str() {
+            "ccache" => {
+                let out = Command::new(&self.program).arg("--print-stats").output().ok()?;
+                out.status.success().then(|| parse_ccache_stats(&String::from_utf8_lossy(&out.stdout)))
+            }
+            "sccache" => {
+                let out = Command::new(&self.program).args(["--show-stats", "--stats-format=json"]).output().ok()?;
+                out.status.success().then(|| parse_sccache_stats(&String::from_utf8_lossy(&out.stdout)))?
+            }
+            _ => None,
+        }
+    }
+
+    // Сводка по кэшу за сборку: разница счётчиков до и после компиляции
+    pub(crate) fn report(&self, before: Option<CacheStats>) {
+        let (Some(before), Some(after)) = (before, self.stats()) else {
+            return;
+        };
+
+        let hits = after.hits.saturating_sub(before.hits);
+        let misses = after.misses.saturating_sub(before.misses);
+        if hits + misses > 0 {
+            crab_status!("Cache", "{} hits, {} misses ({})", hits, misses, self.name());
+        }
+    }
+
+    fn name(&self) -> String {
+        self.program.file_stem().unwrap_or_default().to_string_lossy().to_string()
+    }
+}
+
+// Команда компиляции: через обёртку, если она задана
+pub(crate) fn command(launcher: Option<&Launcher>, compiler: &str) -> Command {
+    match launcher {
+        Some(l) => {
+            let mut cmd = Command::new(&l.program);
+            cmd.args(&l.args).arg(compiler);
+            cmd
+        }
+        None => Command::new(compiler),
+    }
+}
+
+// Поиск программы: путь как есть или по каталогам PATH
+fn find_program(name: &str) -> Option<PathBuf> {
+    let path = Path::new(name);
+    if path.components().count() > 1 {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(format!("{}{}", name, std::env::consts::EXE_SUFFIX)))
+        .find(|p| p.is_file())
+}
+
+// ccache --print-stats: строки "ключ<TAB>значение"
+fn parse_ccache_stats(text: &str) -> CacheStats {
+    let mut stats = CacheStats::default();
+
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once('\t') else { continue };
+        let value: u64 = value.trim().parse().unwrap_or(0);
+
+        match key {
+            "direct_cache_hit" | "preprocessed_cache_hit" => stats.hits += value,
+            "cache_miss" => stats.misses += value,
+            _ => {}
+        }
+    }
+
+    stats
+}
+
+// sccache --show-stats --stats-format=json: {"stats": {"cache_hits": {"counts": {"C/C++": n}}, ...}}
+fn parse_sccache_stats(text: &str) -> Option<CacheStats> {
+    let json: serde_json::Value = serde_json::from_str(text).ok()?;
+    let stats = json.get("stats")?;
+
+    let total = |key: &str| -> u64 {
+        stats.get(key)
+            .and_then(|v| v.get("counts"))
+            .and_then(|c| c.as_object())
+            .map(|c| c.values().filter_map(|v| v.as_u64()).sum())
+            .unwrap_or(0)
+    };
+
+    Some(CacheStats { hits: total("cache_hits"), misses: total("cache_misses") })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ccache_and_sccache_stats() {
+        let ccache = "stats_updated_timestamp\t1700000000\ndirect_cache_hit\t5\npreprocessed_cache_hit\t2\ncache_miss\t3\n";
+        assert_eq!(parse_ccache_stats(ccache), CacheStats { hits: 7, misses: 3 });
+
+        let sccache = r#"{"stats":{"compile_requests":12,"cache_hits":{"counts":{"C/C++":4},"adv_counts":{}},"cache_misses":{"counts":{"C/C++":1,"Assembler":1}}}}"#;
+        assert_eq!(parse_sccache_stats(sccache), Some(CacheStats { hits: 4, misses: 2 }));
+    }
+
+    #[test]
+    fn missing_launcher_is_an_error() {
+        let build = Build { launcher: "crab-no-such-launcher --flag".to_string(), ..Default::default() };
+        let err = Launcher::resolve(&build).err().expect("launcher should not be found");
+        assert!(err.to_string().contains("crab-no-such-launcher"));
+        assert!(Launcher::resolve(&Build::default()).unwrap().is_none());
+    }
+}
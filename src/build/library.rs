@@ -1,23 +1,34 @@
-use std::fs::{self, File};
-use std::io::{BufRead, BufReader};
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::time::Instant;
-use rayon::prelude::*;
 
-use crate::config::{load_config, CrabConfig, CONFIG};
+use crate::config::{load_effective_for, CrabConfig, CONFIG};
+use crate::find::CrabFind;
 use crate::{crab_err, crab_log, crab_status};
+use super::binary::{BuildProfile, CrabBuild};
 use super::helpers::CrabBuildFunc;
+use super::pkgconfig;
+use super::unity;
 use std::io::ErrorKind;
 
 // Тип библиотеки: статическая или динамическая
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 pub enum LibKind {
     Static,
     Dynamic,
 }
 
 impl LibKind {
+    // Библиотека как цель проекта: [library] kind = "static" | "dynamic"
+    pub(crate) fn from_config(config: &CrabConfig) -> Option<LibKind> {
+        match config.library.kind.as_deref() {
+            Some("static") => Some(LibKind::Static),
+            Some("dynamic") => Some(LibKind::Dynamic),
+            _ => None,
+        }
+    }
+
     // Имя подкаталога библиотеки ("static" | "dynamic")
     fn dir(&self) -> &'static str {
         match self {
@@ -25,9 +36,89 @@ impl LibKind {
             LibKind::Dynamic => CONFIG.dynamic_dir,
         }
     }
+
+    // Флаги компиляции объектов библиотеки из [library]: макросы для <name>_export.h
+    // и видимость символов (только для динамической)
+    pub(crate) fn compile_args(&self, config: &CrabConfig) -> Vec<String> {
+        let macro_name = export_macro_prefix(&config.library.output_name(&config.project.name));
+
+        match self {
+            LibKind::Static => vec![format!("-D{}_STATIC", macro_name)],
+            LibKind::Dynamic => {
+                let mut args = vec![format!("-D{}_BUILDING", macro_name)];
+                if config.library.visibility.as_deref() == Some("hidden") {
+                    args.push("-fvisibility=hidden".to_string());
+                    if config.settings.lang != "c" {
+                        args.push("-fvisibility-inlines-hidden".to_string());
+                    }
+                }
+                args
+            }
+        }
+    }
+
+    // Флаги компиляции кода, использующего библиотеку (исполняемые цели, тесты)
+    pub(crate) fn consumer_args(&self, config: &CrabConfig) -> Vec<String> {
+        match self {
+            LibKind::Static => vec![format!("-D{}_STATIC", export_macro_prefix(&config.library.output_name(&config.project.name)))],
+            LibKind::Dynamic => Vec::new(),
+        }
+    }
+
+    // Каталог сборки библиотеки: crb/library/<профиль>/<static|dynamic>
+    pub(crate) fn output_dir(&self, profile: &BuildProfile) -> PathBuf {
+        PathBuf::from(CONFIG.build_dir).join(CONFIG.library_dir).join(profile.dir()).join(self.dir())
+    }
+
+    // Собранный файл библиотеки: lib<name>.a или настоящий файл динамической
+    pub(crate) fn output_file(&self, config: &CrabConfig, profile: &BuildProfile) -> PathBuf {
+        let name = config.library.output_name(&config.project.name);
+        match self {
+            LibKind::Static => self.output_dir(profile).join(format!("lib{}.a", name)),
+            LibKind::Dynamic => self.output_dir(profile).join(SharedNames::new(&name, &config.project.version).real),
+        }
+    }
+
+    // Флаги линковки с библиотекой для бинарников в crb/<каталог>/bin.
+    // Динамическая ищется через rpath: рядом в дереве сборки и в ../lib после установки.
+    pub(crate) fn consumer_link_args(&self, config: &CrabConfig, profile: &BuildProfile) -> Vec<String> {
+        let name = config.library.output_name(&config.project.name);
+
+        match self {
+            LibKind::Static => vec![self.output_file(config, profile).display().to_string()],
+            LibKind::Dynamic => {
+                let mut args = vec![format!("-L{}", self.output_dir(profile).display()), format!("-l{}", name)];
+                let origin = match std::env::consts::OS {
+                    "windows" => return args,
+                    "macos" | "ios" => "@loader_path",
+                    _ => "$ORIGIN",
+                };
+                let build_tree = Path::new("..").join("..").join(CONFIG.library_dir).join(profile.dir()).join(self.dir());
+                args.push(format!("-Wl,-rpath,{}/{}", origin, build_tree.display()));
+                args.push(format!("-Wl,-rpath,{}/../lib", origin));
+                args
+            }
+        }
+    }
+
+    // На Windows rpath нет: DLL копируется к исполняемым файлам
+    pub(crate) fn place_runtime(&self, config: &CrabConfig, profile: &BuildProfile, bin_dir: &Path) -> std::io::Result<()> {
+        if *self == LibKind::Static || !cfg!(windows) {
+            return Ok(());
+        }
+
+        let dll = self.output_file(config, profile);
+        if let Some(file_name) = dll.file_name() {
+            fs::copy(&dll, bin_dir.join(file_name))?;
+        }
+
+        Ok(())
+    }
 }
 
-pub struct CrabLib;
+pub struct CrabLib {
+    unity: bool,
+}
 
 impl Default for CrabLib {
     fn default() -> Self {
@@ -37,145 +128,231 @@ impl Default for CrabLib {
 
 impl CrabLib {
     pub fn new() -> Self {
-        CrabLib
+        CrabLib { unity: false }
     }
 
-    // Создание директорий для библиотеки
-    fn create_build_lib_dir(&self, kind: LibKind) -> std::io::Result<()> {
-        let path_to_lib_dir = PathBuf::from(CONFIG.build_dir).join(CONFIG.library_dir).join(kind.dir());
-        let path_to_dep = PathBuf::from(CONFIG.build_dir).join(CONFIG.library_dir).join(CONFIG.dependencies);
+    // crab build --unity: объединять исходники даже без [build] unity
+    pub fn with_unity(mut self, unity: bool) -> Self {
+        self.unity = unity;
+        self
+    }
+
+    // Проверка [library]: допустимая видимость и не более одного способа задать экспорт
+    fn check_library_config(&self, config: &CrabConfig) -> std::io::Result<()> {
+        let lib = &config.library;
+
+        if let Some(k) = lib.kind.as_deref()
+            && !["static", "dynamic", "header-only"].contains(&k) {
+                crab_err!(ErrorKind::InvalidInput, "[library] kind must be \"static\", \"dynamic\" or \"header-only\", got \"{}\"", k);
+            }
+
+        if lib.is_header_only() {
+            crab_err!(ErrorKind::Unsupported, "{} is a header-only library: there is nothing to archive or link, `crab build` checks its headers", config.project.name);
+        }
 
-        crab_log!("INFO", "LIB", "Checking the existence of a directory for library: {}", path_to_lib_dir.display());
-        if !path_to_lib_dir.exists() {
-            crab_log!("INFO", "LIB", "The directory does not exist, create: {}", path_to_lib_dir.display());
-            fs::create_dir_all(path_to_lib_dir)?;
+        if let Some(v) = lib.visibility.as_deref()
+            && v != "hidden" && v != "default" {
+                crab_err!(ErrorKind::InvalidInput, "[library] visibility must be \"hidden\" or \"default\", got \"{}\"", v);
+            }
+
+        if lib.version_script.is_some() && lib.exports.is_some() {
+            crab_err!(ErrorKind::InvalidInput, "[library] version_script and exports cannot be used together");
         }
-        crab_log!("INFO", "LIB", "Checking for the existence of a dependency file: {}", path_to_dep.display());
-        if !path_to_dep.exists() {
-            crab_log!("INFO", "LIB", "The file does not exist, create: {}", path_to_dep.display());
-            File::create(path_to_dep)?;
+
+        for file in [&lib.version_script, &lib.exports].into_iter().flatten() {
+            if !Path::new(file).is_file() {
+                crab_err!(ErrorKind::NotFound, "Export file not found: {}", file);
+            }
         }
 
         Ok(())
     }
 
-    // Компиляция исходников в объектные файлы библиотеки (для динамической добавляется -fPIC)
-    fn compiling_library(&self, kind: LibKind) -> std::io::Result<()> {
-        crab_log!("INFO", "LIB", "Compilation to an object file");
-        let path_to_object_dir = PathBuf::from(CONFIG.build_dir).join(CONFIG.library_dir).join(kind.dir()).join(CONFIG.object_dir);
+    // Генерация <header_dir>/<name>_export.h с макросом <NAME>_EXPORT.
+    // Файл перезаписывается только при изменении, чтобы не вызывать лишних пересборок.
+    fn write_export_header(&self, config: &CrabConfig) -> std::io::Result<()> {
+        let name = config.library.output_name(&config.project.name);
+        let dir = Path::new(&config.settings.header_dir);
+        let path = dir.join(format!("{}_export.h", name));
+        let content = export_header(&name);
 
-        if !path_to_object_dir.exists() {
-            crab_log!("INFO", "LIB", "There is no directory for the object files. To create: {}", path_to_object_dir.display());
-            fs::create_dir_all(&path_to_object_dir)?;
+        if fs::read_to_string(&path).is_ok_and(|old| old == content) {
+            return Ok(());
         }
 
-        let config: CrabConfig = load_config(CONFIG.config_file)?;
-        let cbf = CrabBuildFunc::new();
+        fs::create_dir_all(dir)?;
+        fs::write(&path, content)?;
+        crab_status!("Generated", "{}", path.display());
 
-        let compiler = config.settings.compiler;
-        let head = config.settings.header_dir;
-        let lang = config.settings.lang;
-        let is_head = cbf.is_header()?;
-        let is_dynamic = matches!(kind, LibKind::Dynamic);
-        let user_compile = config.build.compile_args();
-        let path_to_dep_file = PathBuf::from(CONFIG.build_dir).join(CONFIG.library_dir).join(CONFIG.dependencies);
+        Ok(())
+    }
 
-        if !path_to_dep_file.exists() {
-            crab_log!("ERROR", "LIB", "The dependency file was not found: {}", path_to_dep_file.display());
-            crab_err!(ErrorKind::NotFound, "The dependency file was not found");
+    // Флаги линковщика для списка экспорта: version_script как есть, exports —
+    // через сгенерированный скрипт версий (или -exported_symbols_list на macOS)
+    fn export_link_args(&self, config: &CrabConfig, path_to_lib: &Path) -> std::io::Result<Vec<String>> {
+        if cfg!(windows) {
+            return Ok(Vec::new());
         }
 
-        let file = fs::File::open(&path_to_dep_file)?;
-        let reader = BufReader::new(&file);
-        let lines: Vec<String> = reader.lines().collect::<std::io::Result<Vec<_>>>()?;
+        if let Some(script) = &config.library.version_script {
+            return Ok(vec![format!("-Wl,--version-script={}", script)]);
+        }
 
-        lines.par_iter().try_for_each(|line| -> std::io::Result<()> {
-            let line = line.trim();
+        let Some(exports) = &config.library.exports else {
+            return Ok(Vec::new());
+        };
+
+        let symbols: Vec<String> = fs::read_to_string(exports)?
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(str::to_string)
+            .collect();
+
+        if cfg!(target_os = "macos") {
+            let list = path_to_lib.join("exports.list");
+            let text: String = symbols.iter().map(|s| format!("_{}\n", s)).collect();
+            fs::write(&list, text)?;
+            return Ok(vec![format!("-Wl,-exported_symbols_list,{}", list.display())]);
+        }
 
-            if line.is_empty() {
-                return Ok(());
-            }
+        let script = path_to_lib.join("exports.map");
+        fs::write(&script, version_script(&symbols))?;
+        Ok(vec![format!("-Wl,--version-script={}", script.display())])
+    }
 
-            let result = cbf.split_dep(line, &lang)?;
+    // Объектные файлы библиотеки в стабильном (отсортированном) порядке
+    pub(crate) fn collect_objects(&self, path_to_obj: &Path) -> std::io::Result<Vec<String>> {
+        let mut objects = Vec::new();
 
-            if !result[0].ends_with(".o") {
-                return Ok(());
+        for entry in fs::read_dir(path_to_obj)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "o") {
+                objects.push(path.display().to_string());
             }
+        }
 
-            let path_to_obj = format!("{}/{}/{}/{}/{}", CONFIG.build_dir, CONFIG.library_dir, kind.dir(), CONFIG.object_dir, result[0]);
-            crab_status!("Compiling", "{}", &result[1]);
+        objects.sort();
+        Ok(objects)
+    }
 
-            let mut args: Vec<String> = vec!["-c".to_string()];
-            // -fPIC нужен только на Unix; на Windows он бессмысленен и вызывает предупреждение
-            if is_dynamic && !cfg!(windows) {
-                args.push("-fPIC".to_string());
-            }
-            args.push(result[1].clone());
-            args.push("-o".to_string());
-            args.push(path_to_obj);
-            if is_head {
-                args.push(format!("-I{}", head));
-            }
-            args.extend(user_compile.iter().cloned());
+    // Архив актуален, если он новее всех объектов и состоит ровно из них (ar t)
+    fn archive_is_fresh(&self, archive: &Path, objects: &[String]) -> bool {
+        let Ok(archive_time) = fs::metadata(archive).and_then(|m| m.modified()) else {
+            return false;
+        };
+
+        let newer = objects.iter().any(|o| {
+            fs::metadata(o).and_then(|m| m.modified()).map_or(true, |t| t > archive_time)
+        });
+        if newer {
+            return false;
+        }
 
-            cbf.output_wrapper(Command::new(&compiler).args(&args).output())
-        })?;
+        let Ok(out) = Command::new("ar").arg("t").arg(archive).output() else {
+            return false;
+        };
+        let mut members: Vec<String> = String::from_utf8_lossy(&out.stdout).lines().map(str::to_string).collect();
+        members.sort();
 
-        Ok(())
+        out.status.success() && members == archive_members(objects)
     }
 
-    // Создание архива для статической библиотеки
-    fn create_archive(&self) -> std::io::Result<()> {
-        crab_log!("INFO", "LIB", "Create static library");
+    // Сборка одного статического архива из всех объектов.
+    // Архив пересоздаётся с нуля (без устаревших членов) с индексом символов (s);
+    // D — детерминированный режим GNU ar (нулевые метки времени и uid/gid).
+    pub(crate) fn archive(&self, objects: &[String], archive: &Path) -> std::io::Result<bool> {
+        if self.archive_is_fresh(archive, objects) {
+            crab_log!("INFO", "LIB", "Archive is up to date: {}", archive.display());
+            return Ok(false);
+        }
+
+        if archive.exists() {
+            fs::remove_file(archive)?;
+        }
+
+        let mode = if cfg!(target_os = "macos") { "rcs" } else { "rcsD" };
         let cbf = CrabBuildFunc::new();
+        cbf.output_wrapper(Command::new("ar").arg(mode).arg(archive).args(objects).output())?;
 
-        let path_to_obj = PathBuf::from(CONFIG.build_dir).join(CONFIG.library_dir).join(CONFIG.static_dir).join(CONFIG.object_dir);
+        Ok(true)
+    }
 
-        for entry in fs::read_dir(path_to_obj)? {
-            let entry = entry?;
+    // Создание статической библиотеки lib<name>.a из всех объектов проекта
+    fn create_archive(&self, config: &CrabConfig, path_to_lib: &Path) -> std::io::Result<()> {
+        crab_log!("INFO", "LIB", "Create static library");
+        let name = config.library.output_name(&config.project.name);
+        let objects = self.collect_objects(&path_to_lib.join(CONFIG.object_dir))?;
 
-            let filename = entry.path().file_stem().unwrap().display().to_string();
-            let lib_file = format!("lib{}.a", filename);
-            let fmt_obj = format!("{}/{}/{}/{}", CONFIG.build_dir, CONFIG.library_dir, CONFIG.static_dir, lib_file);
-            let entry_str = entry.path().display().to_string();
+        if objects.is_empty() {
+            crab_err!(ErrorKind::NotFound, "There are no object files to archive!");
+        }
 
-            cbf.output_wrapper(Command::new("ar").args(["rcs", &fmt_obj, &entry_str]).output())?;
+        let lib_file = format!("lib{}.a", name);
 
-            crab_status!("Archiving", "{}", lib_file);
+        if self.archive(&objects, &path_to_lib.join(&lib_file))? {
+            crab_status!("Archiving", "{} ({} objects)", lib_file, objects.len());
         }
 
         Ok(())
     }
 
-    // Создание динамической библиотеки
-    fn create_dynamic_library(&self) -> std::io::Result<()> {
+    // Создание одной динамической библиотеки из всех объектов проекта:
+    // libX.so.1.2.3 с soname libX.so.1 и цепочкой ссылок libX.so -> libX.so.1 -> libX.so.1.2.3
+    fn create_dynamic_library(&self, config: &CrabConfig, profile: &BuildProfile, path_to_lib: &Path, find: Option<&Path>, relink: bool) -> std::io::Result<()> {
         crab_log!("INFO", "LIB", "Create dynamic library");
         let cbf = CrabBuildFunc::new();
-        let config: CrabConfig = load_config(CONFIG.config_file)?;
-        let compiler = config.settings.compiler;
-        let user_link = config.build.link_args();
-
-        let path_to_obj = PathBuf::from(CONFIG.build_dir).join(CONFIG.library_dir).join(CONFIG.dynamic_dir).join(CONFIG.object_dir);
+        let names = SharedNames::new(&config.library.output_name(&config.project.name), &config.project.version);
+        let objects = self.collect_objects(&path_to_lib.join(CONFIG.object_dir))?;
 
-        for entry in fs::read_dir(path_to_obj)? {
-            let entry = entry?;
+        if objects.is_empty() {
+            crab_err!(ErrorKind::NotFound, "There are no object files to link!");
+        }
 
-            let filename = entry.path().file_stem().unwrap().display().to_string();
-            // имя по платформе: libX.so / libX.dylib / X.dll
-            let lib_file = format!("{}{}{}", std::env::consts::DLL_PREFIX, filename, std::env::consts::DLL_SUFFIX);
-            let fmt_obj = format!("{}/{}/{}/{}", CONFIG.build_dir, CONFIG.library_dir, CONFIG.dynamic_dir, lib_file);
-            let entry_str = entry.path().display().to_string();
+        let real = path_to_lib.join(&names.real);
 
-            cbf.output_wrapper(Command::new(&compiler).args(["-shared", &entry_str, "-o", &fmt_obj]).args(&user_link).output())?;
+        // изменённый файл экспорта тоже требует перелинковки
+        let real_time = fs::metadata(&real).and_then(|m| m.modified()).ok();
+        let exports_changed = [&config.library.version_script, &config.library.exports]
+            .into_iter()
+            .flatten()
+            .any(|f| fs::metadata(f).and_then(|m| m.modified()).ok() > real_time);
 
-            crab_status!("Linking", "{}", lib_file);
+        if !relink && !exports_changed && real.exists() {
+            crab_log!("INFO", "LIB", "Shared library is up to date: {}", real.display());
+            return names.link_chain(path_to_lib);
         }
 
+        // Сторонние библиотеки (-L/-l) — только по явному [library].link_deps
+        let (dep_paths, dep_names) = match find {
+            Some(data) if config.library.link_deps => CrabBuild::new().read_lib_path_and_fmt(data)?,
+            _ => (Vec::new(), Vec::new()),
+        };
+
+        cbf.output_wrapper(Command::new(&config.settings.compiler)
+            .arg("-shared")
+            .args(&objects)
+            .arg("-o")
+            .arg(&real)
+            .args(names.soname_args())
+            .args(self.export_link_args(config, path_to_lib)?)
+            .args(profile.link_flags())
+            .args(&dep_paths)
+            .args(&dep_names)
+            .args(config.build.link_args())
+            .args(profile.sanitizer_flags())
+            .output())?;
+
+        names.link_chain(path_to_lib)?;
+
+        crab_status!("Linking", "{}", names.real);
+
         Ok(())
     }
 
-    // Сборка библиотеки (статической или динамической)
-    pub fn build_lib(&self, kind: LibKind) -> std::io::Result<()> {
+    // Сборка библиотеки (статической или динамической) в заданном профиле.
+    // Объекты пересобираются инкрементально, как у бинарника (get_changed_files).
+    pub fn build_lib(&self, kind: LibKind, profile: BuildProfile) -> std::io::Result<()> {
         let crb = CrabBuildFunc::new();
 
         crb.is_compiler()?;
@@ -184,62 +361,362 @@ impl CrabLib {
 
         crab_log!("INFO", "LIB", "START LIBRARY BUILDING");
 
-        self.create_build_lib_dir(kind)?;
+        let flag = profile.dir();
+        let base = kind.output_dir(&profile);
+        crb.create_target_dir(&base, false)?;
 
-        let config: CrabConfig = load_config(CONFIG.config_file)?;
-        let lang = config.settings.lang;
-        let source_dir = config.settings.source_dir;
-        let path = Path::new(source_dir.as_str());
+        let mut config = load_effective_for(&flag)?;
+        unity::force(&mut config, self.unity);
+        self.check_library_config(&config)?;
+        self.write_export_header(&config)?;
 
-        crab_status!("Compiling", "{} [{} library]", config.project.name, kind.dir());
+        let lang = config.settings.lang.clone();
+        let source_dir = config.settings.source_dir.clone();
+        let path = Path::new(source_dir.as_str());
 
         let mut source: Vec<String> = Vec::new();
 
-        if lang == "c" {
-            CrabBuildFunc::collect_file_with_extension(path, "c", &mut source)?;
-        } else {
-            CrabBuildFunc::collect_file_with_extension(path, "cpp", &mut source)?;
-        }
+        CrabBuildFunc::collect_sources(path, &lang, &mut source)?;
+
+        // исходники исполняемых целей [[bin]] и модулей в библиотеку не входят
+        source.retain(|s| !config.is_bin_source(s) && !config.is_module_source(s));
 
         if source.is_empty() {
             crab_err!(ErrorKind::NotFound, "There are no files to build!");
         }
 
         crb.write_file_in_config(&source)?;
-
-        crb.check_ignore_files(&mut source)?;
+        crb.check_ignore_files(&flag, &mut source)?;
 
         if source.is_empty() {
             crab_err!(ErrorKind::NotFound, "There are no files to build!");
         }
 
-        crb.write_dependencies(kind.dir(), &source)?;
+        let finder = CrabFind::new(".", &flag);
+        let find = finder.parsing_include()?.then(|| finder.data_dir());
+
+        let path_dep = base.join(CONFIG.dependencies);
+        let path_obj = base.join(CONFIG.object_dir);
+        let path_obj_data = base.join(CONFIG.object_data);
+
+        let source = unity::combine(&config, &base, source)?;
+        crb.write_dependencies(&config, &path_dep, &source, find)?;
 
-        self.compiling_library(kind)?;
+        let signature = CrabBuild::new().signature(&config, &profile, find, Some(kind))?;
+        let changed = crb.get_changed_files(&path_obj_data, &path_dep, &source, &lang, &signature)?;
+
+        if !changed.is_empty() {
+            crab_status!("Compiling", "{} v{} [{} library, {}]", config.project.name, config.project.version, kind.dir(), flag);
+            CrabBuild::new().compile_to_object(&config, &profile, &base, find, &changed, Some(kind))?;
+        }
 
         // Убираем .o от удалённых исходников, чтобы они не попали в библиотеку
-        let path_dep = PathBuf::from(CONFIG.build_dir).join(CONFIG.library_dir).join(CONFIG.dependencies);
-        let path_obj = PathBuf::from(CONFIG.build_dir).join(CONFIG.library_dir).join(kind.dir()).join(CONFIG.object_dir);
-        crb.prune_orphan_objects(&path_dep, &path_obj)?;
+        let pruned = crb.prune_orphan_objects(&path_dep, &path_obj)?;
 
         match kind {
-            LibKind::Static => self.create_archive()?,
-            LibKind::Dynamic => self.create_dynamic_library()?,
+            LibKind::Static => self.create_archive(&config, &base)?,
+            LibKind::Dynamic => self.create_dynamic_library(&config, &profile, &base, find, !changed.is_empty() || pruned)?,
         }
 
-        crab_status!("Finished", "{} library in {:.2}s", kind.dir(), start.elapsed().as_secs_f64());
+        pkgconfig::write_build_pc(&config, kind, &base)?;
+
+        crab_status!("Finished", "{} library [{}] in {:.2}s", kind.dir(), flag, start.elapsed().as_secs_f64());
         crab_log!("INFO", "LIB", "End of the library build");
 
         Ok(())
     }
 
     // Статическая библиотека
-    pub fn static_lib_build(&self) -> std::io::Result<()> {
-        self.build_lib(LibKind::Static)
+    pub fn static_lib_build(&self, profile: BuildProfile) -> std::io::Result<()> {
+        self.build_lib(LibKind::Static, profile)
     }
 
     // Динамическая библиотека
-    pub fn dynamic_lib_build(&self) -> std::io::Result<()> {
-        self.build_lib(LibKind::Dynamic)
+    pub fn dynamic_lib_build(&self, profile: BuildProfile) -> std::io::Result<()> {
+        self.build_lib(LibKind::Dynamic, profile)
+    }
+}
+
+// Имена файлов динамической библиотеки по платформе и project.version
+#[derive(Debug, PartialEq)]
+pub(crate) struct SharedNames {
+    pub link: String,   // libX.so — то, что находит -lX
+    pub soname: String, // libX.so.1 — записывается в библиотеку, по нему ищет загрузчик
+    pub real: String,   // libX.so.1.2.3 — настоящий файл
+    os: &'static str,
+}
+
+impl SharedNames {
+    pub(crate) fn new(name: &str, version: &str) -> Self {
+        Self::for_os(name, version, std::env::consts::OS)
+    }
+
+    fn for_os(name: &str, version: &str, os: &'static str) -> Self {
+        let version = version.trim();
+        let major = version.split('.').next().unwrap_or("");
+
+        let (link, soname, real) = match os {
+            // на Windows версий в имени нет: X.dll
+            "windows" => {
+                let dll = format!("{}.dll", name);
+                (dll.clone(), dll.clone(), dll)
+            }
+            "macos" | "ios" => {
+                let link = format!("lib{}.dylib", name);
+                if version.is_empty() {
+                    (link.clone(), link.clone(), link)
+                } else {
+                    (link, format!("lib{}.{}.dylib", name, major), format!("lib{}.{}.dylib", name, version))
+                }
+            }
+            _ => {
+                let link = format!("lib{}.so", name);
+                if version.is_empty() {
+                    (link.clone(), link.clone(), link)
+                } else {
+                    (link.clone(), format!("{}.{}", link, major), format!("{}.{}", link, version))
+                }
+            }
+        };
+
+        Self { link, soname, real, os }
+    }
+
+    // Флаги линковщика, записывающие soname (install_name на macOS)
+    pub(crate) fn soname_args(&self) -> Vec<String> {
+        match self.os {
+            "windows" => Vec::new(),
+            "macos" | "ios" => vec![format!("-Wl,-install_name,@rpath/{}", self.soname)],
+            _ => vec![format!("-Wl,-soname,{}", self.soname)],
+        }
+    }
+
+    // Ссылки link -> soname -> real в каталоге dir (на Windows не нужны)
+    pub(crate) fn link_chain(&self, dir: &Path) -> std::io::Result<()> {
+        #[cfg(unix)]
+        {
+            for (from, to) in [(&self.soname, &self.real), (&self.link, &self.soname)] {
+                if from == to {
+                    continue;
+                }
+                let path = dir.join(from);
+                if path.symlink_metadata().is_ok() {
+                    fs::remove_file(&path)?;
+                }
+                std::os::unix::fs::symlink(to, &path)?;
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = dir;
+
+        Ok(())
+    }
+}
+
+// Префикс макросов экспорта: "my-lib" -> "MY_LIB"
+pub(crate) fn export_macro_prefix(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect()
+}
+
+// Содержимое <name>_export.h: <NAME>_EXPORT помечает публичный API.
+// При сборке динамической библиотеки определён <NAME>_BUILDING, статической — <NAME>_STATIC
+// (его же должны определять потребители статической библиотеки на Windows).
+fn export_header(name: &str) -> String {
+    let m = export_macro_prefix(name);
+    format!(
+"// Generated by crab. Do not edit.
+#ifndef {m}_EXPORT_H
+#define {m}_EXPORT_H
+
+#if defined({m}_STATIC)
+#  define {m}_EXPORT
+#elif defined(_WIN32) || defined(__CYGWIN__)
+#  if defined({m}_BUILDING)
+#    define {m}_EXPORT __declspec(dllexport)
+#  else
+#    define {m}_EXPORT __declspec(dllimport)
+#  endif
+#else
+#  define {m}_EXPORT __attribute__((visibility(\"default\")))
+#endif
+
+#endif // {m}_EXPORT_H
+")
+}
+
+// Скрипт версий GNU ld: наружу только перечисленные символы
+fn version_script(symbols: &[String]) -> String {
+    let mut out = String::from("{\n  global:\n");
+    for s in symbols {
+        out.push_str(&format!("    {};\n", s));
+    }
+    out.push_str("  local:\n    *;\n};\n");
+    out
+}
+
+// Имена членов архива для набора объектов (ar хранит только имена файлов)
+fn archive_members(objects: &[String]) -> Vec<String> {
+    let mut names: Vec<String> = objects
+        .iter()
+        .filter_map(|o| Path::new(o).file_name().map(|n| n.to_string_lossy().into_owned()))
+        .collect();
+    names.sort();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_names_follow_version() {
+        let n = SharedNames::for_os("foo", "1.2.3", "linux");
+        assert_eq!((n.link.as_str(), n.soname.as_str(), n.real.as_str()), ("libfoo.so", "libfoo.so.1", "libfoo.so.1.2.3"));
+        assert_eq!(n.soname_args(), vec!["-Wl,-soname,libfoo.so.1"]);
+
+        let n = SharedNames::for_os("foo", "1.2.3", "macos");
+        assert_eq!((n.link.as_str(), n.soname.as_str(), n.real.as_str()), ("libfoo.dylib", "libfoo.1.dylib", "libfoo.1.2.3.dylib"));
+
+        let n = SharedNames::for_os("foo", "1.2.3", "windows");
+        assert_eq!(n.real, "foo.dll");
+        assert!(n.soname_args().is_empty());
+    }
+
+    #[test]
+    fn shared_names_without_version_are_unversioned() {
+        let n = SharedNames::for_os("foo", "", "linux");
+        assert_eq!(n.real, "libfoo.so");
+        assert_eq!(n.soname, "libfoo.so");
+    }
+
+    #[test]
+    fn export_header_uses_sanitized_macro_prefix() {
+        assert_eq!(export_macro_prefix("my-lib.core"), "MY_LIB_CORE");
+        let h = export_header("foo");
+        assert!(h.contains("#  define FOO_EXPORT __attribute__((visibility(\"default\")))"));
+        assert!(h.contains("#if defined(FOO_STATIC)"));
+    }
+
+    #[test]
+    fn version_script_hides_everything_else() {
+        let script = version_script(&["foo_init".to_string(), "foo_run".to_string()]);
+        assert_eq!(script, "{\n  global:\n    foo_init;\n    foo_run;\n  local:\n    *;\n};\n");
+    }
+
+    fn config() -> CrabConfig {
+        toml::from_str(
+            "[project]\nname = \"p\"\nversion = \"0.1.0\"\ncreated = 2024\n\
+             [settings]\nlang = \"c++\"\ncompiler = \"g++\"\nsource_dir = \"src\"\nheader_dir = \"include\"\n\
+             [library]\nkind = \"static\"\n",
+        ).unwrap()
+    }
+
+    fn touch(path: &Path, secs: u64) {
+        let time = std::time::SystemTime::now() + std::time::Duration::from_secs(secs);
+        fs::File::options().write(true).open(path).unwrap().set_modified(time).unwrap();
+    }
+
+    #[test]
+    fn objects_live_in_per_profile_kind_dirs() {
+        let lib = Path::new(CONFIG.build_dir).join(CONFIG.library_dir);
+        assert_eq!(LibKind::Static.output_dir(&BuildProfile::Debug), lib.join("debug").join("static"));
+        assert_eq!(LibKind::Dynamic.output_dir(&BuildProfile::Release), lib.join("release").join("dynamic"));
+
+        // debug и release не делят объекты и сам архив
+        let config = config();
+        assert_eq!(LibKind::Static.output_file(&config, &BuildProfile::Release), lib.join("release").join("static").join("libp.a"));
+        assert_ne!(LibKind::Static.output_file(&config, &BuildProfile::Debug), LibKind::Static.output_file(&config, &BuildProfile::Release));
+    }
+
+    #[test]
+    fn library_objects_rebuild_incrementally() {
+        let base = std::env::temp_dir().join(format!("crab_test_lib_changed_{}", std::process::id()));
+        fs::create_dir_all(&base).unwrap();
+        let a = base.join("a.cpp");
+        let b = base.join("b.cpp");
+        fs::write(&a, "int a() { return 1; }\n").unwrap();
+        fs::write(&b, "int b() { return 2; }\n").unwrap();
+
+        let path_dep = base.join(CONFIG.dependencies);
+        let path_obj_data = base.join(CONFIG.object_data);
+        fs::write(&path_dep, format!("a.o: {}\nb.o: {}\n", a.display(), b.display())).unwrap();
+        let sources = vec![a.display().to_string(), b.display().to_string()];
+
+        let config = config();
+        let debug = CrabBuild::new().signature(&config, &BuildProfile::Debug, None, Some(LibKind::Static)).unwrap();
+        let changed = |signature: &str| CrabBuildFunc::new().get_changed_files(&path_obj_data, &path_dep, &sources, "c++", signature).unwrap();
+
+        assert_eq!(changed(&debug), sources);
+        assert!(changed(&debug).is_empty());
+
+        touch(&b, 60);
+        assert_eq!(changed(&debug), vec![sources[1].clone()]);
+
+        // флаги другого профиля — пересобирается всё
+        let release = CrabBuild::new().signature(&config, &BuildProfile::Release, None, Some(LibKind::Static)).unwrap();
+        assert_eq!(changed(&release), sources);
+
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn signature_tracks_detected_libraries_and_language() {
+        let data = std::env::temp_dir().join(format!("crab_test_lib_signature_{}", std::process::id()));
+        fs::create_dir_all(&data).unwrap();
+        let build = CrabBuild::new();
+        let config = config();
+        let signature = |config: &CrabConfig, find: Option<&Path>| build.signature(config, &BuildProfile::Debug, find, None).unwrap();
+
+        fs::write(data.join(CONFIG.include_file), "/tmp/vend/debug/include\n").unwrap();
+        fs::write(data.join(CONFIG.lib_file), "-L/tmp/vend/debug/lib\n-lfoo\n").unwrap();
+        let debug = signature(&config, Some(&data));
+        assert!(debug.contains("-I/tmp/vend/debug/include") && debug.contains("-L/tmp/vend/debug/lib"));
+
+        // другой профиль нашёл библиотеку в другом месте — объекты пересобираются
+        fs::write(data.join(CONFIG.include_file), "/tmp/vend/release/include\n").unwrap();
+        assert_ne!(signature(&config, Some(&data)), debug);
+
+        let mut c = config.clone();
+        c.settings.lang = "c".to_string();
+        assert_ne!(signature(&c, None), signature(&config, None));
+
+        let _ = fs::remove_dir_all(&data);
+    }
+
+    #[test]
+    fn fresh_archive_is_not_recreated() {
+        let base = std::env::temp_dir().join(format!("crab_test_lib_archive_{}", std::process::id()));
+        fs::create_dir_all(&base).unwrap();
+        let objects: Vec<String> = ["a.o", "b.o"].iter().map(|o| {
+            let path = base.join(o);
+            fs::write(&path, *o).unwrap();
+            path.display().to_string()
+        }).collect();
+        let archive = base.join("libp.a");
+        let lib = CrabLib::new();
+
+        assert!(lib.archive(&objects, &archive).unwrap());
+        assert!(lib.archive_is_fresh(&archive, &objects));
+        assert!(!lib.archive(&objects, &archive).unwrap());
+
+        // объект новее архива
+        touch(Path::new(&objects[1]), 60);
+        assert!(!lib.archive_is_fresh(&archive, &objects));
+        assert!(lib.archive(&objects, &archive).unwrap());
+
+        // объект исчез из сборки — архив пересоздаётся без него
+        touch(&archive, 120);
+        assert!(!lib.archive_is_fresh(&archive, &objects[..1]));
+
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn archive_members_are_sorted_file_names() {
+        let objs = vec![
+            "crb/library/static/obj/net.o".to_string(),
+            "crb/library/static/obj/core.o".to_string(),
+        ];
+        assert_eq!(archive_members(&objs), vec!["core.o", "net.o"]);
     }
 }
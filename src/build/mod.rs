@@ -4,9 +4,20 @@ mod library;
 mod compdb;
 mod test;
 mod check;
+mod abi;
+mod pkgconfig;
+mod targets;
+mod pch;
+mod unity;
+mod cxx_modules;
+mod launcher;
 
 pub use binary::{BuildProfile, CrabBuild};
 pub use library::{CrabLib, LibKind};
 pub use compdb::CrabCompDb;
 pub use test::CrabTest;
 pub use check::CrabCheck;
+pub use abi::CrabAbi;
+pub(crate) use helpers::{is_entry_point, CrabBuildFunc};
+pub(crate) use library::SharedNames;
+pub(crate) use pkgconfig::PkgConfig;
@@ -0,0 +1,161 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::cfg::CfgContext;
+use crate::config::{Build, CrabConfig, CONFIG};
+use crate::{crab_err, crab_log, crab_status};
+use super::helpers::CrabBuildFunc;
+
+// Предкомпилированный заголовок ([build] pch). В каталоге сборки лежит заглушка
+// с #include на заголовок проекта и её .gch (.pch у clang); единицы трансляции
+// получают -include <заглушка>. Компилятор берёт готовый .gch, а если флаги файла
+// с ним не совпадают (напр. из [[build.override]]) — включает заголовок как обычно.
+pub(crate) struct Pch {
+    stub: PathBuf,
+    out: PathBuf,
+}
+
+impl Pch {
+    // Заглушка в <base>/pch/ (перезаписывается только при изменении); None — pch не задан
+    pub(crate) fn prepare(config: &CrabConfig, base: &Path) -> std::io::Result<Option<Self>> {
+        let header = config.build.pch.trim();
+        if header.is_empty() {
+            return Ok(None);
+        }
+
+        let path = Path::new(header);
+        let Some(name) = path.file_name().filter(|_| path.is_file()) else {
+            crab_err!(ErrorKind::NotFound, "Precompiled header not found: {}", header);
+        };
+
+        let dir = base.join(CONFIG.pch_dir);
+        fs::create_dir_all(&dir)?;
+
+        let stub = dir.join(name);
+        let content = format!("#include \"{}\"\n", std::path::absolute(path)?.display().to_string().replace('\\', "/"));
+        if fs::read_to_string(&stub).ok().as_deref() != Some(content.as_str()) {
+            fs::write(&stub, content)?;
+        }
+
+        let ext = if CfgContext::new(&config.settings.compiler, None).compiler_family == "clang" { "pch" } else { "gch" };
+        let out = dir.join(format!("{}.{}", name.to_string_lossy(), ext));
+
+        Ok(Some(Self { stub, out }))
+    }
+
+    // -include для компиляции и для -MM: заголовок и его включения попадают в зависимости каждого файла
+    pub(crate) fn include_args(&self) -> Vec<String> {
+        vec!["-include".to_string(), self.stub.display().to_string()]
+    }
+
+    // Компиляция заголовка с общими флагами единиц трансляции args.
+    // Пересобирается, если .gch нет, флаги изменились или заголовок (с включениями) новее.
+    pub(crate) fn build(&self, compiler: &str, lang: &str, args: &[String]) -> std::io::Result<()> {
+        let deps = self.out.with_extension("d");
+        let sig = self.out.with_extension("sig");
+        let signature = format!("{} {}", compiler, args.join(" "));
+
+        if fs::read_to_string(&sig).is_ok_and(|s| s == signature) && is_fresh(&self.out, &deps) {
+            crab_log!("INFO", "BUILD", "Precompiled header is up to date: {}", self.out.display());
+            return Ok(());
+        }
+
+        crab_status!("Compiling", "{} (precompiled header)", self.stub.file_name().unwrap_or_default().to_string_lossy());
+
+        CrabBuildFunc::new().output_wrapper(Command::new(compiler)
+            .args(args)
+            .arg("-x")
+            .arg(if lang == "c" { "c-header" } else { "c++-header" })
+            .arg(&self.stub)
+            .arg("-o")
+            .arg(&self.out)
+            .arg("-MMD")
+            .arg("-MF")
+            .arg(&deps)
+            .output())?;
+
+        fs::write(&sig, signature)
+    }
+}
+
+// -include на сам заголовок — для проверок и compile_commands.json, где .gch не нужен
+pub(crate) fn header_args(build: &Build) -> Vec<String> {
+    match build.pch.trim() {
+        "" => Vec::new(),
+        header => vec!["-include".to_string(), header.to_string()],
+    }
+}
+
+// .gch новее всех файлов из его make-зависимостей (-MMD)
+fn is_fresh(out: &Path, deps: &Path) -> bool {
+    let (Ok(built), Ok(content)) = (fs::metadata(out).and_then(|m| m.modified()), fs::read_to_string(deps)) else {
+        return false;
+    };
+
+    let joined = content.replace("\\\r\n", " ").replace("\\\n", " ");
+    // цель отделяется двоеточием с пробелом после него: двоеточие диска (C:\...) — часть пути
+    let prereqs = joined.match_indices(':')
+        .find(|(i, _)| joined[i + 1..].chars().next().is_none_or(char::is_whitespace))
+        .map(|(i, _)| &joined[i + 1..])
+        .unwrap_or_default();
+
+    prereqs.split_whitespace().all(|f| fs::metadata(f).and_then(|m| m.modified()).is_ok_and(|t| t <= built))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_fresh_compares_with_every_prerequisite() {
+        let dir = std::env::temp_dir().join(format!("crab_test_pch_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let header = dir.join("pch.hpp");
+        let out = dir.join("pch.hpp.gch");
+        let deps = dir.join("pch.hpp.d");
+
+        fs::write(&header, "").unwrap();
+        assert!(!is_fresh(&out, &deps));
+
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        fs::write(&out, "").unwrap();
+        fs::write(&deps, format!("{}: {} \\\n {}\n", out.display(), header.display(), header.display())).unwrap();
+        assert!(is_fresh(&out, &deps));
+
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        fs::write(&header, "// changed").unwrap();
+        assert!(!is_fresh(&out, &deps));
+
+        // цель с буквой диска, как пишет компилятор на Windows
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        fs::write(&out, "").unwrap();
+        fs::write(&deps, format!("C:\\build\\pch.hpp.gch: {}\n", header.display())).unwrap();
+        assert!(is_fresh(&out, &deps));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn prepare_picks_pch_for_clang_and_gch_for_gcc() {
+        let dir = std::env::temp_dir().join(format!("crab_test_pch_ext_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let header = dir.join("pch.hpp");
+        fs::write(&header, "").unwrap();
+
+        let mut config: CrabConfig = toml::from_str(
+            "[project]\nname = \"p\"\nversion = \"0.1.0\"\ncreated = 2024\n\
+             [settings]\nlang = \"c++\"\ncompiler = \"g++\"\nsource_dir = \"src\"\nheader_dir = \"include\"\n",
+        ).unwrap();
+        config.build.pch = header.display().to_string();
+
+        for (compiler, ext) in [("clang++", "pch"), ("/usr/bin/clang++-17", "pch"), ("g++", "gch"), ("gcc", "gch")] {
+            config.settings.compiler = compiler.to_string();
+            let pch = Pch::prepare(&config, &dir).unwrap().expect("pch is set");
+            assert_eq!(pch.out.extension().and_then(|e| e.to_str()), Some(ext), "{}", compiler);
+        }
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,182 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{CrabConfig, CONFIG};
+use crate::find::CrabFind;
+use crate::{crab_log, crab_status};
+use super::binary::CrabBuild;
+use super::library::LibKind;
+
+// Описание библиотеки для <name>.pc
+#[derive(Debug)]
+pub(crate) struct PkgConfig {
+    pub name: String,
+    pub version: String,
+    pub requires: Vec<String>,     // пакеты pkg-config, от которых зависит библиотека
+    pub libs_private: Vec<String>, // сторонние библиотеки, найденные без pkg-config (-L/-l)
+    pub cflags: Vec<String>,       // дополнительные флаги потребителя (напр. -D<NAME>_STATIC)
+    pub header_only: bool,         // без -l<name>: своей библиотеки нет
+}
+
+impl PkgConfig {
+    // Сведения о библиотеке проекта: имя, версия и зависимости из последнего детекта
+    pub(crate) fn from_project(config: &CrabConfig, kind: LibKind) -> std::io::Result<Self> {
+        let name = config.library.output_name(&config.project.name);
+
+        let header_only = config.library.is_header_only();
+
+        let cflags = if header_only { Vec::new() } else { kind.consumer_args(config) };
+
+        Ok(Self {
+            version: config.project.version.clone(),
+            requires: CrabFind::read_pkg_names()?,
+            libs_private: fallback_libs()?,
+            cflags,
+            header_only,
+            name,
+        })
+    }
+
+    // Текст .pc; prefix, libdir и includedir — абсолютные пути (каталог сборки или установки)
+    pub(crate) fn render(&self, prefix: &Path, libdir: &Path, includedir: &Path) -> String {
+        let mut out = format!("prefix={}\n", prefix.display());
+        out.push_str(&format!("libdir={}\n", relative_to_prefix(prefix, libdir)));
+        out.push_str(&format!("includedir={}\n\n", relative_to_prefix(prefix, includedir)));
+
+        out.push_str(&format!("Name: {}\n", self.name));
+        out.push_str(&format!("Description: {} library\n", self.name));
+        out.push_str(&format!("Version: {}\n", self.version));
+        if !self.requires.is_empty() {
+            out.push_str(&format!("Requires: {}\n", self.requires.join(", ")));
+        }
+
+        let mut cflags = vec!["-I${includedir}".to_string()];
+        cflags.extend(self.cflags.iter().cloned());
+        out.push_str(&format!("Cflags: {}\n", cflags.join(" ")));
+        // у header-only своих объектов нет: сторонние библиотеки нужны потребителю напрямую
+        if self.header_only {
+            if !self.libs_private.is_empty() {
+                out.push_str(&format!("Libs: {}\n", self.libs_private.join(" ")));
+            }
+            return out;
+        }
+
+        out.push_str(&format!("Libs: -L${{libdir}} -l{}\n", self.name));
+        if !self.libs_private.is_empty() {
+            out.push_str(&format!("Libs.private: {}\n", self.libs_private.join(" ")));
+        }
+
+        out
+    }
+
+    // Запись <dir>/<name>.pc (только при изменении содержимого); true — файл записан
+    pub(crate) fn write(&self, dir: &Path, content: &str) -> std::io::Result<(PathBuf, bool)> {
+        let path = dir.join(format!("{}.pc", self.name));
+
+        if fs::read_to_string(&path).is_ok_and(|old| old == content) {
+            return Ok((path, false));
+        }
+
+        fs::create_dir_all(dir)?;
+        fs::write(&path, content)?;
+        crab_log!("INFO", "LIB", "Wrote pkg-config file: {}", path.display());
+
+        Ok((path, true))
+    }
+}
+
+// .pc рядом со свежесобранной библиотекой: libdir — каталог сборки, includedir — header_dir.
+// Годится для PKG_CONFIG_PATH=<каталог> без установки.
+pub(crate) fn write_build_pc(config: &CrabConfig, kind: LibKind, lib_dir: &Path) -> std::io::Result<()> {
+    let root = std::env::current_dir()?;
+    let pc = PkgConfig::from_project(config, kind)?;
+    let content = pc.render(&root, &root.join(lib_dir), &root.join(&config.settings.header_dir));
+    let (path, written) = pc.write(&lib_dir.join("pkgconfig"), &content)?;
+    if written {
+        crab_status!("Generated", "{}", path.display());
+    }
+    Ok(())
+}
+
+// Пути внутри prefix записываются через ${prefix}, чтобы .pc оставался переносимым
+fn relative_to_prefix(prefix: &Path, path: &Path) -> String {
+    match path.strip_prefix(prefix) {
+        Ok(rest) if rest.as_os_str().is_empty() => "${prefix}".to_string(),
+        Ok(rest) => format!("${{prefix}}/{}", rest.display().to_string().replace('\\', "/")),
+        Err(_) => path.display().to_string(),
+    }
+}
+
+// Сторонние библиотеки в fallback-формате (полные пути к файлам) -> "-L<dir> -l<name>"
+fn fallback_libs() -> std::io::Result<Vec<String>> {
+    let path = PathBuf::from(CONFIG.build_dir).join(CONFIG.data_dir).join(CONFIG.lib_file);
+
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut flags = Vec::new();
+
+    for line in fs::read_to_string(path)?.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('-') {
+            continue;
+        }
+
+        let file = Path::new(line);
+        let (Some(parent), Some(name)) = (file.parent(), file.file_name().and_then(|n| n.to_str())) else {
+            continue;
+        };
+
+        for flag in [format!("-L{}", parent.display()), format!("-l{}", CrabBuild::lib_link_name(name))] {
+            if !flags.contains(&flag) {
+                flags.push(flag);
+            }
+        }
+    }
+
+    Ok(flags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_uses_prefix_relative_dirs_and_requires() {
+        let pc = PkgConfig {
+            name: "foo".to_string(),
+            version: "1.2.3".to_string(),
+            requires: vec!["sdl2".to_string(), "zlib".to_string()],
+            libs_private: Vec::new(),
+            cflags: vec!["-DFOO_STATIC".to_string()],
+            header_only: false,
+        };
+
+        let text = pc.render(Path::new("/opt/foo"), Path::new("/opt/foo/lib"), Path::new("/opt/foo/include"));
+
+        assert!(text.starts_with("prefix=/opt/foo\nlibdir=${prefix}/lib\nincludedir=${prefix}/include\n"));
+        assert!(text.contains("Version: 1.2.3\n"));
+        assert!(text.contains("Requires: sdl2, zlib\n"));
+        assert!(text.contains("Cflags: -I${includedir} -DFOO_STATIC\n"));
+        assert!(text.contains("Libs: -L${libdir} -lfoo\n"));
+        assert!(!text.contains("Libs.private"));
+    }
+
+    #[test]
+    fn header_only_links_only_dependencies() {
+        let pc = PkgConfig {
+            name: "hdr".to_string(),
+            version: "0.1.0".to_string(),
+            requires: Vec::new(),
+            libs_private: vec!["-lz".to_string()],
+            cflags: Vec::new(),
+            header_only: true,
+        };
+
+        let text = pc.render(Path::new("/usr"), Path::new("/usr/lib"), Path::new("/usr/include"));
+
+        assert!(text.ends_with("Cflags: -I${includedir}\nLibs: -lz\n"));
+        assert!(!text.contains("-lhdr"));
+        assert!(!text.contains("Libs.private"));
+    }
+}
@@ -0,0 +1,328 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Instant, SystemTime};
+
+use crate::config::{BinTarget, CrabConfig, CONFIG};
+use crate::find::CrabFind;
+use crate::glob::{glob_match, is_glob};
+use crate::{crab_err, crab_log, crab_status};
+use super::binary::{BuildProfile, CrabBuild};
+use super::helpers::{is_entry_point, CrabBuildFunc};
+use super::library::{CrabLib, LibKind};
+use super::unity;
+
+// Какие цели [[bin]] собирать
+pub(crate) enum BinSelection<'a> {
+    Default,
+    Named(&'a str),
+    All,
+}
+
+impl CrabBuild {
+    // Сборка проекта с целями: библиотека из [library] kind и исполняемые [[bin]].
+    // Объекты библиотеки собираются один раз в её каталоге, каждая цель — в
+    // crb/<профиль>/target/<имя> со своими флагами и линкуется с готовой библиотекой.
+    pub(crate) fn build_targets(&self, config: &CrabConfig, profile: &BuildProfile, selection: BinSelection) -> std::io::Result<()> {
+        let start = Instant::now();
+        let flag = profile.dir();
+
+        check_bins(&config.bins)?;
+
+        let bins: Vec<&BinTarget> = match selection {
+            BinSelection::Default => config.default_bin().into_iter().collect(),
+            BinSelection::All => config.bins.iter().collect(),
+            BinSelection::Named(name) => match config.bins.iter().find(|b| b.name == name) {
+                Some(bin) => vec![bin],
+                None if config.bins.is_empty() => crab_err!(ErrorKind::NotFound, "No [[bin]] target named {}: the project declares none", name),
+                None => crab_err!(ErrorKind::NotFound, "No [[bin]] target named {} (available: {})",
+                    name, config.bins.iter().map(|b| b.name.as_str()).collect::<Vec<_>>().join(", ")),
+            },
+        };
+
+        let kind = LibKind::from_config(config);
+        if let Some(kind) = kind {
+            CrabLib::new().with_unity(self.unity).build_lib(kind, profile.clone())?;
+        }
+
+        if bins.is_empty() {
+            return Ok(());
+        }
+
+        let finder = CrabFind::new(".", &flag);
+        let find = finder.parsing_include()?.then(|| finder.data_dir());
+
+        let mut candidates = Vec::new();
+        CrabBuildFunc::collect_sources(Path::new(&config.settings.source_dir), &config.settings.lang, &mut candidates)?;
+
+        for bin in bins {
+            self.build_bin(config, profile, kind, bin, &candidates, find)?;
+        }
+
+        if let Some(kind) = kind {
+            kind.place_runtime(config, profile, &PathBuf::from(CONFIG.build_dir).join(&flag).join(CONFIG.binary_dir))?;
+        }
+
+        crab_status!("Finished", "{} target in {:.2}s", flag, start.elapsed().as_secs_f64());
+        crab_log!("INFO", "BUILD", "End of the build");
+
+        Ok(())
+    }
+
+    // Все цели [[bin]] (crab build --all-targets); проект без целей собирается как обычно
+    pub fn build_all_targets(&self, profile: BuildProfile) -> std::io::Result<()> {
+        let mut config = crate::config::load_effective_for(&profile.dir())?;
+        unity::force(&mut config, self.unity);
+
+        if config.library.is_header_only() || (LibKind::from_config(&config).is_none() && config.bins.is_empty()) {
+            return self.building(profile, None, None);
+        }
+
+        CrabBuildFunc::new().is_compiler()?;
+        self.build_targets(&config, &profile, BinSelection::All)
+    }
+
+    // Одна цель: инкрементальная компиляция её исходников и линковка
+    fn build_bin(&self, config: &CrabConfig, profile: &BuildProfile, kind: Option<LibKind>, bin: &BinTarget, candidates: &[String], find: Option<&Path>) -> std::io::Result<()> {
+        let crb = CrabBuildFunc::new();
+        let flag = profile.dir();
+
+        let base = PathBuf::from(CONFIG.build_dir).join(&flag).join(CONFIG.target_dir).join(&bin.name);
+        let bin_dir = PathBuf::from(CONFIG.build_dir).join(&flag).join(CONFIG.binary_dir);
+        crb.create_target_dir(&base, false)?;
+        fs::create_dir_all(&bin_dir)?;
+
+        let mut source = bin_sources(bin, candidates)?;
+        crb.write_file_in_config(&source)?;
+        crb.check_ignore_files(&flag, &mut source)?;
+
+        if source.is_empty() {
+            crab_err!(ErrorKind::NotFound, "[[bin]] target {} has no sources to build", bin.name);
+        }
+
+        let path_dep = base.join(CONFIG.dependencies);
+        let path_obj = base.join(CONFIG.object_dir);
+        // флаги цели поверх [build]
+        let mut config = config.clone();
+        config.build.merge(&bin.build_overlay());
+        let config = &config;
+        let source = unity::combine(config, &base, source)?;
+
+        crb.write_dependencies(config, &path_dep, &source, find)?;
+
+        let signature = self.signature(config, profile, find, None)?;
+        let changed = crb.get_changed_files(&base.join(CONFIG.object_data), &path_dep, &source, &config.settings.lang, &signature)?;
+
+        if !changed.is_empty() {
+            crab_status!("Compiling", "{} [{}]", bin.name, flag);
+            self.compile_to_object(config, profile, &base, find, &changed, None)?;
+        }
+
+        let pruned = crb.prune_orphan_objects(&path_dep, &path_obj)?;
+
+        let out = bin_output(profile, &bin.name);
+        let lib_newer = kind.is_some_and(|k| modified(&out) < modified(&k.output_file(config, profile)));
+
+        if changed.is_empty() && !pruned && !lib_newer && out.exists() {
+            return Ok(());
+        }
+
+        let mut objects = Vec::new();
+        for entry in fs::read_dir(&path_obj)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "o") {
+                objects.push(path.display().to_string());
+            }
+        }
+        objects.sort();
+
+        self.link_bin(config, profile, kind, bin, &objects, find)
+    }
+
+    // Линковка цели: её объекты, библиотека проекта, сторонние библиотеки, [build] с ldflags цели
+    fn link_bin(&self, config: &CrabConfig, profile: &BuildProfile, kind: Option<LibKind>, bin: &BinTarget, objects: &[String], find: Option<&Path>) -> std::io::Result<()> {
+        let out = bin_output(profile, &bin.name);
+        let (paths, names) = match find {
+            Some(data) => self.read_lib_path_and_fmt(data)?,
+            None => (Vec::new(), Vec::new()),
+        };
+        let lib_args = kind.map(|k| k.consumer_link_args(config, profile)).unwrap_or_default();
+
+        crab_status!("Linking", "{}", out.file_name().and_then(|n| n.to_str()).unwrap_or_default());
+        crab_log!("INFO", "BUILD", "Linking {} with {:?}", out.display(), objects);
+
+        CrabBuildFunc::new().output_wrapper(Command::new(&config.settings.compiler)
+            .args(objects)
+            .arg("-o")
+            .arg(&out)
+            .args(lib_args)
+            .args(profile.link_flags())
+            .args(paths)
+            .args(names)
+            .args(config.build.link_args())
+            .args(profile.sanitizer_flags())
+            .output())
+    }
+}
+
+// Имена целей уникальны, у каждой есть sources, default — не больше одной
+fn check_bins(bins: &[BinTarget]) -> std::io::Result<()> {
+    for (i, bin) in bins.iter().enumerate() {
+        if bin.name.trim().is_empty() {
+            crab_err!(ErrorKind::InvalidInput, "[[bin]] target without a name");
+        }
+        if bins[..i].iter().any(|b| b.name == bin.name) {
+            crab_err!(ErrorKind::InvalidInput, "Duplicate [[bin]] target: {}", bin.name);
+        }
+        if bin.sources.is_empty() {
+            crab_err!(ErrorKind::InvalidInput, "[[bin]] target {} has no sources", bin.name);
+        }
+    }
+
+    if bins.iter().filter(|b| b.default).count() > 1 {
+        crab_err!(ErrorKind::InvalidInput, "Only one [[bin]] target can be marked default = true");
+    }
+
+    Ok(())
+}
+
+// Исходники цели: пути берутся как есть (файл обязан существовать),
+// glob-шаблоны раскрываются по исходникам source_dir
+fn bin_sources(bin: &BinTarget, candidates: &[String]) -> std::io::Result<Vec<String>> {
+    let mut sources: Vec<String> = Vec::new();
+
+    for pattern in &bin.sources {
+        let matched: Vec<String> = if is_glob(pattern) {
+            candidates.iter().filter(|c| glob_match(pattern, c)).cloned().collect()
+        } else if Path::new(pattern).is_file() {
+            vec![pattern.trim_start_matches("./").to_string()]
+        } else {
+            crab_err!(ErrorKind::NotFound, "Source of [[bin]] target {} not found: {}", bin.name, pattern);
+        };
+
+        if matched.is_empty() {
+            crab_err!(ErrorKind::NotFound, "Pattern {} of [[bin]] target {} matches no sources", pattern, bin.name);
+        }
+
+        for s in matched {
+            if !sources.contains(&s) {
+                sources.push(s);
+            }
+        }
+    }
+
+    sources.sort();
+    Ok(sources)
+}
+
+// crb/<профиль>/bin/<имя цели>
+pub(crate) fn bin_output(profile: &BuildProfile, name: &str) -> PathBuf {
+    PathBuf::from(CONFIG.build_dir).join(profile.dir()).join(CONFIG.binary_dir).join(format!("{}{}", name, std::env::consts::EXE_SUFFIX))
+}
+
+// Объекты цели по умолчанию без её точки входа — с ними crab test линкует тесты
+// проекта, в котором есть только цели [[bin]]
+pub(crate) fn test_objects(config: &CrabConfig, profile: &BuildProfile) -> std::io::Result<Vec<String>> {
+    let Some(bin) = config.default_bin() else {
+        return Ok(Vec::new());
+    };
+
+    let base = PathBuf::from(CONFIG.build_dir).join(profile.dir()).join(CONFIG.target_dir).join(&bin.name);
+    objects_without_entry(&base, &config.settings.lang)
+}
+
+// Объекты из <base>/obj по .d сборки, кроме объекта исходника с main
+fn objects_without_entry(base: &Path, lang: &str) -> std::io::Result<Vec<String>> {
+    let path_dep = base.join(CONFIG.dependencies);
+    if !path_dep.exists() {
+        crab_err!(ErrorKind::NotFound, "Objects of the [[bin]] target were not found in {}, run crab build first", base.display());
+    }
+
+    let crb = CrabBuildFunc::new();
+    let mut objects = Vec::new();
+
+    for line in fs::read_to_string(&path_dep)?.lines() {
+        let [object, source] = crb.split_dep(line, lang)?;
+        if !object.ends_with(".o") || is_entry_point(&source) {
+            continue;
+        }
+
+        let path = base.join(CONFIG.object_dir).join(&object);
+        if path.exists() {
+            objects.push(path.display().to_string());
+        }
+    }
+
+    objects.sort();
+    Ok(objects)
+}
+
+fn modified(path: &Path) -> SystemTime {
+    path.metadata().and_then(|m| m.modified()).unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bin(sources: &[&str]) -> BinTarget {
+        toml::from_str(&format!("name = \"tool\"\nsources = {:?}", sources)).unwrap()
+    }
+
+    #[test]
+    fn bin_sources_expand_globs_over_candidates() {
+        let candidates = vec![
+            "src/tools/a.cpp".to_string(),
+            "src/tools/b.cpp".to_string(),
+            "src/core.cpp".to_string(),
+        ];
+
+        let sources = bin_sources(&bin(&["src/tools/*.cpp"]), &candidates).unwrap();
+        assert_eq!(sources, vec!["src/tools/a.cpp", "src/tools/b.cpp"]);
+
+        assert!(bin_sources(&bin(&["src/cli/*.cpp"]), &candidates).is_err());
+        assert!(bin_sources(&bin(&["src/missing.cpp"]), &candidates).is_err());
+    }
+
+    #[test]
+    fn test_objects_skip_entry_point_of_target() {
+        let base = std::env::temp_dir().join(format!("crab_test_bin_objs_{}", std::process::id()));
+        let obj = base.join(CONFIG.object_dir);
+        fs::create_dir_all(&obj).unwrap();
+
+        // точка входа цели — cli.cpp, а не main.cpp
+        let cli = base.join("cli.cpp");
+        let util = base.join("util.cpp");
+        fs::write(&cli, "int main() { return 0; }\n").unwrap();
+        fs::write(&util, "int twice(int a) { return a * 2; }\n").unwrap();
+        for name in ["cli.o", "util.o"] {
+            fs::write(obj.join(name), "").unwrap();
+        }
+        fs::write(base.join(CONFIG.dependencies), format!("cli.o: {} include/a.h\nutil.o: {}\n", cli.display(), util.display())).unwrap();
+
+        let objects = objects_without_entry(&base, "c++").unwrap();
+        assert_eq!(objects, vec![obj.join("util.o").display().to_string()]);
+
+        // цель ещё не собрана — понятная ошибка
+        fs::remove_file(base.join(CONFIG.dependencies)).unwrap();
+        let err = objects_without_entry(&base, "c++").unwrap_err();
+        assert!(err.to_string().contains("[[bin]]"));
+
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn check_bins_rejects_duplicates_and_two_defaults() {
+        let mut a = bin(&["a.cpp"]);
+        let mut b = bin(&["b.cpp"]);
+        assert!(check_bins(&[a.clone(), b.clone()]).is_err());
+
+        b.name = "other".to_string();
+        assert!(check_bins(&[a.clone(), b.clone()]).is_ok());
+
+        a.default = true;
+        b.default = true;
+        assert!(check_bins(&[a, b]).is_err());
+    }
+}
@@ -4,11 +4,13 @@ use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::time::Instant;
 
-use crate::config::{load_config, CrabConfig, CONFIG};
+use crate::config::{load_effective_for, CONFIG};
 use crate::find::CrabFind;
 use crate::{crab_err, crab_log, crab_status};
 use super::binary::{BuildProfile, CrabBuild};
 use super::helpers::CrabBuildFunc;
+use super::launcher::{self, Launcher};
+use super::library::LibKind;
 
 pub struct CrabTest;
 
@@ -26,11 +28,13 @@ impl CrabTest {
     // Сборка и запуск тестов из каталога tests/ (или настроенного в [test].dir).
     // filter — опциональная подстрока: запускать только тесты, в имени файла которых она есть.
     pub fn run_tests(&self, filter: Option<&str>, release: bool) -> std::io::Result<()> {
-        let config: CrabConfig = load_config(CONFIG.config_file)?;
+        let profile = if release { BuildProfile::Release } else { BuildProfile::Debug };
+        let config = load_effective_for(&profile.dir())?;
         let test_dir    = config.test.dir.clone();
         let lang        = config.settings.lang.clone();
         let compiler    = config.settings.compiler.clone();
         let header_dir  = config.settings.header_dir.clone();
+        let launcher    = Launcher::resolve(&config.build)?;
 
         crab_log!("INFO", "TEST", "test dir: {}, lang: {}", test_dir, lang);
 
@@ -62,16 +66,28 @@ impl CrabTest {
             }
         }
 
+        // header-only библиотека: тестам нечего линковать из проекта, достаточно заголовков
+        let header_only = config.library.is_header_only();
+
         // Инкрементально собираем основной проект (объектные файлы должны быть актуальны)
-        let profile = if release { BuildProfile::Release } else { BuildProfile::Debug };
-        CrabBuild::new().building(profile.clone(), None, None)?;
+        if !header_only {
+            CrabBuild::new().building(profile.clone(), None, None)?;
+        }
 
         // Каталоги с .o файлами основного проекта
         let obj_dir = PathBuf::from(CONFIG.build_dir).join(profile.dir()).join(CONFIG.object_dir);
 
-        // Объектные файлы проекта без main.o (чтобы не было конфликта символа main)
+        // Библиотека проекта ([library] kind): тесты линкуются с ней, а не с объектами
+        let lib_kind = LibKind::from_config(&config);
+        let lib_link = lib_kind.map(|k| k.consumer_link_args(&config, &profile)).unwrap_or_default();
+        let lib_cflags = lib_kind.map(|k| k.consumer_args(&config)).unwrap_or_default();
+
+        // Объектные файлы проекта без main.o (чтобы не было конфликта символа main);
+        // у проекта из одних [[bin]] — объекты цели по умолчанию без её точки входа
         let mut project_objs: Vec<String> = Vec::new();
-        if obj_dir.exists() {
+        if !header_only && lib_kind.is_none() && !config.bins.is_empty() {
+            project_objs = super::targets::test_objects(&config, &profile)?;
+        } else if !header_only && lib_kind.is_none() && obj_dir.exists() {
             for entry in fs::read_dir(&obj_dir)? {
                 let entry = entry?;
                 let path  = entry.path();
@@ -89,12 +105,13 @@ impl CrabTest {
 
         // Флаги для сторонних библиотек (из детекта)
         let build     = CrabBuild::new();
-        let is_find   = CrabFind::new(".").parsing_include()?;
-        let inc_flags = if is_find { build.read_include_files_and_fmt()? } else { Vec::new() };
-        let (lib_paths, lib_names) = if is_find { build.read_lib_path_and_fmt()? } else { (Vec::new(), Vec::new()) };
+        let finder    = CrabFind::new(".", &profile.dir());
+        let is_find   = finder.parsing_include()?;
+        let inc_flags = if is_find { build.read_include_files_and_fmt(finder.data_dir())? } else { Vec::new() };
+        let (lib_paths, lib_names) = if is_find { build.read_lib_path_and_fmt(finder.data_dir())? } else { (Vec::new(), Vec::new()) };
 
         let cbf = CrabBuildFunc::new();
-        let is_head = cbf.is_header()?;
+        let is_head = cbf.is_header(&config)?;
 
         let profile_cflags: Vec<String> = profile.compile_flags().iter().map(|s| s.to_string()).collect();
         let profile_lflags: Vec<String> = profile.link_flags().iter().map(|s| s.to_string()).collect();
@@ -107,6 +124,9 @@ impl CrabTest {
         let test_bin = test_out.join("bin");
         fs::create_dir_all(&test_obj)?;
         fs::create_dir_all(&test_bin)?;
+        if let Some(kind) = lib_kind {
+            kind.place_runtime(&config, &profile, &test_bin)?;
+        }
 
         let exe_suffix = std::env::consts::EXE_SUFFIX;
         let start      = Instant::now();
@@ -135,10 +155,12 @@ impl CrabTest {
                 cargs.push(format!("-I{}", header_dir));
             }
             cargs.extend(inc_flags.clone());
+            cargs.extend(lib_cflags.clone());
             cargs.extend(profile_cflags.clone());
             cargs.extend(user_compile.clone());
+            cargs.extend(config.build.file_args(tf));
 
-            let cout = Command::new(&compiler).args(&cargs).output()?;
+            let cout = launcher::command(launcher.as_ref(), &compiler).args(&cargs).output()?;
             if !cout.status.success() {
                 println!("  test {} ... {}", tf, crate::color::paint("31", "FAILED (compile error)"));
                 eprint!("{}", String::from_utf8_lossy(&cout.stderr));
@@ -151,6 +173,7 @@ impl CrabTest {
             largs.extend(project_objs.clone());
             largs.push("-o".to_string());
             largs.push(bin_path.display().to_string());
+            largs.extend(lib_link.clone());
             largs.extend(profile_lflags.clone());
             largs.extend(lib_paths.clone());
             largs.extend(lib_names.clone());
@@ -0,0 +1,151 @@
+use std::fs;
+use std::path::Path;
+
+use crate::config::{CrabConfig, Unity, CONFIG};
+use crate::crab_log;
+use crate::glob::glob_match;
+use super::cxx_modules;
+use super::helpers::{is_assembly, is_entry_point};
+
+// crab build --unity: unity-сборка и без секции в конфиге, пачки по умолчанию
+pub(crate) fn force(config: &mut CrabConfig, enabled: bool) {
+    if enabled {
+        config.build.unity.get_or_insert_with(Unity::default);
+    }
+}
+
+// Замена исходников объединёнными файлами <base>/unity/unity_<n>.cpp (.c для C).
+// Каждый включает до batch исходников через #include и перезаписывается только
+// при изменении состава, поэтому инкрементальная сборка по .d работает как обычно.
+// Файлы из exclude, с флагами [[build.override]], с модулями C++20, ассемблер и точка входа
+// (main.c/main.cpp или файл с main) остаются отдельными: crab test убирает её объект из линковки.
+pub(crate) fn combine(config: &CrabConfig, base: &Path, source: Vec<String>) -> std::io::Result<Vec<String>> {
+    let dir = base.join(CONFIG.unity_dir);
+
+    let Some(unity) = config.build.unity.clone() else {
+        // unity выключили: старые объединённые файлы больше не нужны
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        return Ok(source);
+    };
+
+    let (mut solo, mut pooled): (Vec<String>, Vec<String>) = source.into_iter().partition(|s| {
+        unity.exclude.iter().any(|p| glob_match(p, s)) || !config.build.file_args(s).is_empty() || is_assembly(s) || is_entry_point(s) || cxx_modules::uses_modules(s)
+    });
+    pooled.sort();
+
+    fs::create_dir_all(&dir)?;
+    let ext = if config.settings.lang == "c" { "c" } else { "cpp" };
+
+    let mut units = Vec::new();
+    let mut written = Vec::new();
+
+    for (i, batch) in batches(&pooled, unity.batch).into_iter().enumerate() {
+        // одиночный файл объединять не с чем
+        if let [single] = batch {
+            units.push(single.clone());
+            continue;
+        }
+
+        let mut content = String::from("// Generated by crab for the unity build, do not edit\n");
+        for s in batch {
+            content.push_str(&format!("#include \"{}\"\n", std::path::absolute(s)?.display().to_string().replace('\\', "/")));
+        }
+
+        let path = dir.join(format!("unity_{}.{}", i, ext));
+        if fs::read_to_string(&path).ok().as_deref() != Some(content.as_str()) {
+            fs::write(&path, content)?;
+        }
+
+        written.push(path.clone());
+        units.push(path.display().to_string());
+    }
+
+    // пачек могло стать меньше — лишние файлы удаляем
+    for entry in fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if !written.contains(&path) {
+            fs::remove_file(&path)?;
+        }
+    }
+
+    crab_log!("INFO", "BUILD", "Unity build: {} sources in {} units, {} excluded", pooled.len(), units.len(), solo.len());
+
+    units.append(&mut solo);
+    Ok(units)
+}
+
+// Разбиение на пачки не больше batch (batch = 0 считается за 1)
+fn batches(source: &[String], batch: usize) -> Vec<&[String]> {
+    source.chunks(batch.max(1)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Build, FileOverride};
+
+    fn names(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("src/f{}.cpp", i)).collect()
+    }
+
+    #[test]
+    fn batches_split_sources_by_size() {
+        let source = names(5);
+        let split = batches(&source, 2);
+        assert_eq!(split.len(), 3);
+        assert_eq!(split[2], ["src/f4.cpp".to_string()]);
+        assert_eq!(batches(&source, 0).len(), 5);
+    }
+
+    #[test]
+    fn combine_keeps_excluded_and_overridden_files_separate() {
+        let base = std::env::temp_dir().join(format!("crab_test_unity_{}", std::process::id()));
+        let mut config: CrabConfig = toml::from_str(
+            "[project]\nname = \"p\"\nversion = \"0.1.0\"\ncreated = 2024\n\
+             [settings]\nlang = \"c++\"\ncompiler = \"g++\"\nsource_dir = \"src\"\nheader_dir = \"include\"\n",
+        ).unwrap();
+        config.build = Build {
+            unity: Some(Unity { batch: 3, exclude: vec!["src/f1.cpp".to_string()] }),
+            overrides: vec![FileOverride { pattern: "src/f2.cpp".to_string(), cflags: vec!["-w".to_string()], ..Default::default() }],
+            ..Default::default()
+        };
+
+        let units = combine(&config, &base, names(5)).unwrap();
+        let unity_0 = base.join("unity").join("unity_0.cpp");
+        assert_eq!(units, vec![unity_0.display().to_string(), "src/f1.cpp".to_string(), "src/f2.cpp".to_string()]);
+
+        let content = fs::read_to_string(&unity_0).unwrap();
+        assert!(content.contains("src/f0.cpp\"") && content.contains("src/f3.cpp\"") && content.contains("src/f4.cpp\""));
+
+        // пачка из одного файла не объединяется, лишний unity_1 удаляется
+        config.build.unity.as_mut().unwrap().batch = 1;
+        fs::write(base.join("unity").join("unity_1.cpp"), "").unwrap();
+        let units = combine(&config, &base, names(2)).unwrap();
+        assert_eq!(units, vec!["src/f0.cpp".to_string(), "src/f1.cpp".to_string()]);
+        assert!(!base.join("unity").join("unity_1.cpp").exists());
+
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn combine_keeps_main_as_its_own_object() {
+        let base = std::env::temp_dir().join(format!("crab_test_unity_main_{}", std::process::id()));
+        let mut config: CrabConfig = toml::from_str(
+            "[project]\nname = \"p\"\nversion = \"0.1.0\"\ncreated = 2024\n\
+             [settings]\nlang = \"c++\"\ncompiler = \"g++\"\nsource_dir = \"src\"\nheader_dir = \"include\"\n",
+        ).unwrap();
+        config.build.unity = Some(Unity::default());
+
+        // иначе crab test линковал бы unity_0.o с main проекта вместе с main теста
+        let mut source = names(3);
+        source.push("src/main.cpp".to_string());
+        let units = combine(&config, &base, source).unwrap();
+        assert_eq!(units.len(), 2);
+        assert_eq!(units[1], "src/main.cpp");
+        assert!(!fs::read_to_string(&units[0]).unwrap().contains("main.cpp"));
+
+        let _ = fs::remove_dir_all(&base);
+    }
+}
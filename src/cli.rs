@@ -5,8 +5,8 @@ use clap::{Parser, Subcommand, ValueEnum};
 
 use crab::crab_err;
 use crab::config::{CrabUpdateINI, CONFIG};
-use crab::build::{BuildProfile, CrabBuild, CrabCheck, CrabCompDb, CrabLib, CrabTest};
-use crab::project::{CrabClean, CrabInstall, CrabProject, CrabRun, CrabTree, CrabWatch};
+use crab::build::{BuildProfile, CrabAbi, CrabBuild, CrabCheck, CrabCompDb, CrabLib, CrabTest};
+use crab::project::{CrabClean, CrabInstall, CrabProject, CrabRun, CrabTree, CrabUninstall, CrabWatch};
 use crab::module::CrabModule;
 use crab::fmt::CrabFmt;
 use std::io::ErrorKind;
@@ -68,13 +68,17 @@ enum Commands {
         /// Create a library project (src/<name>.cpp + include/<name>.hpp, no main)
         #[arg(long, conflicts_with = "cli")]
         lib: bool,
+
+        /// Create a header-only library project (include/<name>.hpp only)
+        #[arg(long, conflicts_with_all = ["cli", "lib"])]
+        header_only: bool,
     },
 
     /// Initialize a project in the current folder
     Init,
 
     /// Compile the project (debug by default)
-    #[command(alias = "b", after_help = "Examples:\n  crab build\n  crab build release\n  crab build --sanitize asan\n  crab build --sanitize asan,ubsan\n  crab build module net -r\n  crab build lib static")]
+    #[command(alias = "b", after_help = "Examples:\n  crab build\n  crab build release\n  crab build --sanitize asan\n  crab build --sanitize asan,ubsan\n  crab build module net -r\n  crab build lib static\n  crab build lib dynamic --release\n  crab build --sanitize address lib static\n  crab build --bin tool\n  crab build release --all-targets\n  crab build --all-modules\n  crab build release --unity")]
     Build {
         #[command(subcommand)]
         action: Option<BuildAction>,
@@ -82,10 +86,26 @@ enum Commands {
         /// Build with sanitizers: asan, ubsan, tsan, msan (comma-separated for multiple)
         #[arg(long, value_name = "SANITIZER")]
         sanitize: Option<String>,
+
+        /// Build only the given [[bin]] target
+        #[arg(long, value_name = "NAME", global = true)]
+        bin: Option<String>,
+
+        /// Build every [[bin]] target instead of the default one
+        #[arg(long, conflicts_with = "bin", global = true)]
+        all_targets: bool,
+
+        /// Build every registered module (independent ones in parallel)
+        #[arg(long, conflicts_with_all = ["bin", "all_targets"], global = true)]
+        all_modules: bool,
+
+        /// Compile sources in combined batches (unity build), [build] unity or 8 files per batch
+        #[arg(long, global = true)]
+        unity: bool,
     },
 
     /// Build (if needed) and run the binary or a module
-    #[command(after_help = "Examples:\n  crab run\n  crab run -r\n  crab run -m net\n  crab run --sanitize asan\n  crab run -- arg1 arg2")]
+    #[command(after_help = "Examples:\n  crab run\n  crab run -r\n  crab run -m net\n  crab run --bin tool\n  crab run --sanitize asan\n  crab run -- arg1 arg2")]
     Run {
         /// Run the release build instead of debug
         #[arg(long, short = 'r')]
@@ -95,6 +115,10 @@ enum Commands {
         #[arg(long, short = 'm', value_name = "NAME")]
         module: Option<String>,
 
+        /// Run the given [[bin]] target instead of the default one
+        #[arg(long, value_name = "NAME", conflicts_with = "module")]
+        bin: Option<String>,
+
         /// Build and run with sanitizers (asan, ubsan, tsan, msan)
         #[arg(long, value_name = "SANITIZER")]
         sanitize: Option<String>,
@@ -126,25 +150,45 @@ enum Commands {
         action: ConfAction,
     },
 
-    /// Manage modules (add, remove, build)
-    #[command(alias = "m", after_help = "Examples:\n  crab module add net\n  crab module remove net")]
+    /// Manage modules (add, remove, list)
+    #[command(alias = "m", after_help = "Examples:\n  crab module add net\n  crab module list\n  crab module remove net\n  crab module prune")]
     Module {
         #[command(subcommand)]
         action: ModuleAction,
     },
 
-    /// Build (release) and install the binary to ~/.local/bin
-    #[command(after_help = "Examples:\n  crab install\n  crab install --path /usr/local/bin\n  crab install --debug")]
+    /// Build (release) and install the binary to ~/.local/bin, or a full layout into a prefix
+    #[command(after_help = "Examples:\n  crab install\n  crab install --path /usr/local/bin\n  crab install --debug\n  crab install --prefix /opt/foo\n  DESTDIR=/tmp/stage crab install --prefix /usr")]
     Install {
         /// Destination directory (default: ~/.local/bin on Unix, %USERPROFILE%\\.local\\bin on Windows)
         #[arg(long, short = 'p', value_name = "PATH")]
         path: Option<String>,
 
+        /// Install into PREFIX/{bin,lib,include,lib/pkgconfig} (honors DESTDIR)
+        #[arg(long, value_name = "PREFIX", conflicts_with = "path")]
+        prefix: Option<String>,
+
         /// Install the debug build instead of release
         #[arg(long)]
         debug: bool,
     },
 
+    /// Remove files recorded by a previous `crab install`
+    #[command(after_help = "Examples:\n  crab uninstall\n  crab uninstall myapp\n  crab uninstall --prefix /opt/foo\n  crab uninstall --force")]
+    Uninstall {
+        /// Project name (default: the project in the current directory)
+        #[arg(value_name = "NAME")]
+        name: Option<String>,
+
+        /// Choose the installation when the project is installed in several places
+        #[arg(long, value_name = "PREFIX")]
+        prefix: Option<String>,
+
+        /// Also remove files that were modified since installation
+        #[arg(long)]
+        force: bool,
+    },
+
     /// Print the #include dependency tree
     Tree,
 
@@ -184,6 +228,13 @@ enum Commands {
         release: bool,
     },
 
+    /// Record or check the exported symbols (ABI) of the shared library
+    #[command(after_help = "Examples:\n  crab abi dump\n  crab abi check\n  crab abi check -r")]
+    Abi {
+        #[command(subcommand)]
+        action: AbiAction,
+    },
+
     /// Format C/C++ sources with clang-format
     #[command(alias = "f", after_help = "Examples:\n  crab fmt\n  crab fmt --check\n  crab fmt --style Google")]
     Fmt {
@@ -209,7 +260,27 @@ enum ConfAction {
         /// Set the compiler
         #[arg(long, value_name = "COMPILER")]
         compiler: Option<Compiler>,
-    }
+    },
+
+    /// Print effective settings and the file each value comes from (follows `extends`)
+    Show,
+}
+
+#[derive(Subcommand)]
+enum AbiAction {
+    /// Build the shared library and write its exported symbols to abi.toml
+    Dump {
+        /// Use the release build
+        #[arg(long, short = 'r')]
+        release: bool,
+    },
+
+    /// Compare the current build with abi.toml; fail on removals without a major version bump
+    Check {
+        /// Use the release build
+        #[arg(long, short = 'r')]
+        release: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -228,7 +299,14 @@ enum ModuleAction {
         /// Module name
         #[arg(value_name = "NAME")]
         name: String,
-    }
+    },
+
+    /// List modules with their sources, outputs and build status
+    #[command(alias = "l")]
+    List,
+
+    /// Remove modules whose directories no longer exist
+    Prune,
 }
 
 #[derive(Subcommand)]
@@ -258,6 +336,10 @@ enum BuildAction {
     Lib {
         #[arg(value_enum, value_name = "MODE")]
         mode: LibMode,
+
+        /// Build the library in release mode
+        #[arg(long, short = 'r')]
+        release: bool,
     },
 }
 
@@ -328,7 +410,7 @@ pub fn run() -> std::io::Result<()> {
     }
 
     match cli.command {
-        Commands::New { name, git, lang, cli, lib } => {
+        Commands::New { name, git, lang, cli, lib, header_only } => {
             if !is_valid_project_name(&name) {
                 crab_err!(ErrorKind::InvalidFilename, "Invalid project name: {}", name);
             }
@@ -342,7 +424,9 @@ pub fn run() -> std::io::Result<()> {
                 Lang::Cpp => "c++"
             };
 
-            if lib {
+            if header_only {
+                CrabProject::new(&name).create_header_only(git, lang_str)?;
+            } else if lib {
                 CrabProject::new(&name).create_lib(git, lang_str)?;
             } else {
                 CrabProject::new(&name).create(git, lang_str, cli)?;
@@ -353,44 +437,65 @@ pub fn run() -> std::io::Result<()> {
             CrabProject::new("None").init()?;
         }
 
-        Commands::Build { action, sanitize } => {
+        Commands::Build { action, sanitize, bin, all_targets, all_modules, unity } => {
             if !Path::new(CONFIG.config_file).exists() {
                 crab_err!(ErrorKind::Other, "The current directory is not a project");
             }
 
-            if let Some(san) = sanitize {
-                CrabBuild::new().building(BuildProfile::Sanitize(san), None, None)?;
-            } else {
-                let build_mode = CrabBuild::new();
+            if (bin.is_some() || all_targets || all_modules) && matches!(action, Some(BuildAction::Module { .. } | BuildAction::Lib { .. })) {
+                crab_err!(ErrorKind::InvalidInput, "--bin, --all-targets and --all-modules apply to debug, release and sanitizer builds");
+            }
+
+            // профиль сборки целей: --bin выбирает одну, --all-targets — все, --all-modules — модули
+            let build_profile = |profile: BuildProfile| -> std::io::Result<()> {
+                if all_modules {
+                    CrabModule::new().with_unity(unity).build_all(profile)
+                } else if all_targets {
+                    CrabBuild::new().with_unity(unity).build_all_targets(profile)
+                } else {
+                    CrabBuild::new().with_unity(unity).building(profile, None, bin.as_deref())
+                }
+            };
 
+            if let Some(BuildAction::Lib { mode, .. }) = &action
+                && let Some(san) = &sanitize {
+                    let profile = BuildProfile::Sanitize(san.clone());
+                    match mode {
+                        LibMode::Static => CrabLib::new().with_unity(unity).static_lib_build(profile)?,
+                        LibMode::Dynamic => CrabLib::new().with_unity(unity).dynamic_lib_build(profile)?,
+                    }
+            } else if let Some(san) = sanitize {
+                build_profile(BuildProfile::Sanitize(san))?;
+            } else {
                 match action.unwrap_or(BuildAction::Debug) {
                     BuildAction::Debug => {
-                        build_mode.debug_building(None, None)?;
+                        build_profile(BuildProfile::Debug)?;
                     }
 
                     BuildAction::Release => {
-                        build_mode.release_building(None, None)?;
+                        build_profile(BuildProfile::Release)?;
                     }
 
                     BuildAction::Module { name, release } => {
                         if release {
-                            CrabModule::new().build_module(&name, "release")?;
+                            CrabModule::new().with_unity(unity).build_module(&name, "release")?;
                         } else {
-                            CrabModule::new().build_module(&name, "debug")?;
+                            CrabModule::new().with_unity(unity).build_module(&name, "debug")?;
                         }
                     }
 
-                    BuildAction::Lib { mode } => {
+                    BuildAction::Lib { mode, release } => {
+                        let profile = if release { BuildProfile::Release } else { BuildProfile::Debug };
                         match mode {
-                            LibMode::Static => CrabLib::new().static_lib_build()?,
-                            LibMode::Dynamic => CrabLib::new().dynamic_lib_build()?,
+                            LibMode::Static => CrabLib::new().with_unity(unity).static_lib_build(profile)?,
+                            LibMode::Dynamic => CrabLib::new().with_unity(unity).dynamic_lib_build(profile)?,
                         }
                     }
                 }
             }
         }
 
-        Commands::Run { release, module, sanitize, mut args, gdb, valgrind } => {
+        Commands::Run { release, module, bin, sanitize, mut args, gdb, valgrind } => {
             if !Path::new(CONFIG.config_file).exists() {
                 crab_err!(ErrorKind::Other, "The current directory is not a project");
             }
@@ -401,8 +506,7 @@ pub fn run() -> std::io::Result<()> {
                 // Санитайзерная сборка + запуск
                 let profile = BuildProfile::Sanitize(san.clone());
                 let run_dir = profile.dir();
-                CrabBuild::new().building(profile, None, None)?;
-                // UBSan без print_stacktrace почти бесполезен — включаем автоматически
+                CrabBuild::new().building(profile, None, bin.as_deref())?;
                 // UBSan без print_stacktrace почти бесполезен — включаем автоматически.
                 // SAFETY: однопоточный контекст до первого spawn/rayon.
                 if san.contains("undefined") {
@@ -410,7 +514,7 @@ pub fn run() -> std::io::Result<()> {
                         std::env::set_var("UBSAN_OPTIONS", "print_stacktrace=1:halt_on_error=1");
                     }
                 }
-                runner.run(&run_dir, &mut args, gdb, valgrind)?;
+                runner.run(&run_dir, bin.as_deref(), &mut args, gdb, valgrind)?;
             } else {
                 let mode = if release { "release" } else { "debug" };
 
@@ -419,11 +523,11 @@ pub fn run() -> std::io::Result<()> {
                     runner.run_module(&module_name, mode, &mut args, gdb, valgrind)?;
                 } else {
                     if release {
-                        CrabBuild::new().release_building(None, None)?;
+                        CrabBuild::new().release_building(None, bin.as_deref())?;
                     } else {
-                        CrabBuild::new().debug_building(None, None)?;
+                        CrabBuild::new().debug_building(None, bin.as_deref())?;
                     }
-                    runner.run(mode, &mut args, gdb, valgrind)?;
+                    runner.run(mode, bin.as_deref(), &mut args, gdb, valgrind)?;
                 }
             }
         }
@@ -474,6 +578,14 @@ pub fn run() -> std::io::Result<()> {
                 }
 
             }
+
+            ConfAction::Show => {
+                if !Path::new(CONFIG.config_file).exists() {
+                    crab_err!(ErrorKind::Other, "The current directory is not a project");
+                }
+
+                CrabUpdateINI::new(CONFIG.config_file).show()?;
+            }
         },
 
         Commands::Module { action } => {
@@ -489,15 +601,31 @@ pub fn run() -> std::io::Result<()> {
                 ModuleAction::Remove { name } => {
                     CrabModule::new().remove(&name)?;
                 }
+
+                ModuleAction::List => {
+                    CrabModule::new().list()?;
+                }
+
+                ModuleAction::Prune => {
+                    CrabModule::new().prune()?;
+                }
             }
         }
 
-        Commands::Install { path, debug } => {
+        Commands::Install { path, prefix, debug } => {
             if !Path::new(CONFIG.config_file).exists() {
                 crab_err!(ErrorKind::Other, "The current directory is not a project");
             }
 
-            CrabInstall::new().install(path.as_deref(), debug)?;
+            if let Some(prefix) = prefix {
+                CrabInstall::new().install_prefix(&prefix, debug)?;
+            } else {
+                CrabInstall::new().install(path.as_deref(), debug)?;
+            }
+        }
+
+        Commands::Uninstall { name, prefix, force } => {
+            CrabUninstall::new().uninstall(name.as_deref(), prefix.as_deref(), force)?;
         }
 
         Commands::Tree => {
@@ -537,6 +665,24 @@ pub fn run() -> std::io::Result<()> {
             CrabCheck::new().check(release)?;
         }
 
+        Commands::Abi { action } => {
+            if !Path::new(CONFIG.config_file).exists() {
+                crab_err!(ErrorKind::Other, "The current directory is not a project");
+            }
+
+            match action {
+                AbiAction::Dump { release } => {
+                    let profile = if release { BuildProfile::Release } else { BuildProfile::Debug };
+                    CrabAbi::new().dump(profile)?;
+                }
+
+                AbiAction::Check { release } => {
+                    let profile = if release { BuildProfile::Release } else { BuildProfile::Debug };
+                    CrabAbi::new().check(profile)?;
+                }
+            }
+        }
+
         Commands::Fmt { check, style } => {
             if !Path::new(CONFIG.config_file).exists() {
                 crab_err!(ErrorKind::Other, "The current directory is not a project");
@@ -0,0 +1,301 @@
+use std::io::ErrorKind;
+use std::path::Path;
+
+use super::schema::CrabConfig;
+use crate::crab_err;
+
+// Условие секции [target.'cfg(...)'], например:
+//   cfg(unix)  cfg(windows)  cfg(compiler = "clang")  cfg(os = "linux")
+//   cfg(target = "x86_64-w64-mingw32")  cfg(all(unix, not(compiler = "gcc")))
+#[derive(Debug, PartialEq)]
+pub enum Cfg {
+    Flag(String),
+    Equals(String, String),
+    All(Vec<Cfg>),
+    Any(Vec<Cfg>),
+    Not(Box<Cfg>),
+}
+
+// То, против чего проверяются условия: активный компилятор, ОС и цель сборки
+#[derive(Debug)]
+pub struct CfgContext {
+    pub compiler: String,
+    pub compiler_family: String,
+    pub os: String,
+    pub family: String,
+    pub target: String,
+}
+
+impl CfgContext {
+    // target — явная цель из settings.target; если не задана, берётся из префикса
+    // компилятора (x86_64-w64-mingw32-g++ -> x86_64-w64-mingw32), иначе — хост
+    pub fn new(compiler: &str, target: Option<&str>) -> Self {
+        let name = Path::new(compiler)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| compiler.to_string());
+
+        let compiler_family = if name.contains("clang") {
+            "clang"
+        } else if name.contains("gcc") || name.contains("g++") || name == "cc" || name == "c++" {
+            "gcc"
+        } else {
+            ""
+        };
+
+        let target = target
+            .filter(|t| !t.trim().is_empty())
+            .map(str::to_string)
+            .or_else(|| target_from_compiler(&name))
+            .unwrap_or_default();
+
+        let os = if target.is_empty() {
+            std::env::consts::OS.to_string()
+        } else if target.contains("windows") || target.contains("mingw") {
+            "windows".to_string()
+        } else if target.contains("apple") || target.contains("darwin") {
+            "macos".to_string()
+        } else if target.contains("linux") {
+            "linux".to_string()
+        } else if target.contains("freebsd") {
+            "freebsd".to_string()
+        } else {
+            std::env::consts::OS.to_string()
+        };
+
+        let family = if os == "windows" { "windows" } else { "unix" };
+
+        Self {
+            compiler: name,
+            compiler_family: compiler_family.to_string(),
+            os,
+            family: family.to_string(),
+            target,
+        }
+    }
+
+    fn matches(&self, cfg: &Cfg) -> std::io::Result<bool> {
+        Ok(match cfg {
+            Cfg::Flag(f) => match f.as_str() {
+                "unix" | "windows" => self.family == *f,
+                _ => crab_err!(ErrorKind::InvalidData, "Unknown cfg flag: {}", f),
+            },
+            Cfg::Equals(k, v) => match k.as_str() {
+                "compiler" => self.compiler_family == *v || self.compiler == *v,
+                "os" => self.os == *v,
+                "family" => self.family == *v,
+                "target" => self.target == *v,
+                _ => crab_err!(ErrorKind::InvalidData, "Unknown cfg key: {}", k),
+            },
+            Cfg::All(list) => {
+                for c in list {
+                    if !self.matches(c)? {
+                        return Ok(false);
+                    }
+                }
+                true
+            }
+            Cfg::Any(list) => {
+                for c in list {
+                    if self.matches(c)? {
+                        return Ok(true);
+                    }
+                }
+                false
+            }
+            Cfg::Not(c) => !self.matches(c)?,
+        })
+    }
+}
+
+// Префикс-триплет кросс-компилятора: "aarch64-linux-gnu-gcc" -> "aarch64-linux-gnu"
+fn target_from_compiler(name: &str) -> Option<String> {
+    let (prefix, tool) = name.rsplit_once('-')?;
+    let tool_ok = ["gcc", "g++", "cc", "c++", "clang", "clang++"].contains(&tool);
+    (tool_ok && prefix.matches('-').count() >= 1).then(|| prefix.to_string())
+}
+
+// Разбор ключа секции: строка "cfg(...)"
+pub fn parse_cfg(key: &str) -> std::io::Result<Cfg> {
+    let invalid = || std::io::Error::new(ErrorKind::InvalidData, format!("Invalid target condition: {}", key));
+
+    let inner = key.trim()
+        .strip_prefix("cfg(")
+        .and_then(|s| s.strip_suffix(')'))
+        .ok_or_else(invalid)?;
+
+    let mut parser = Parser { input: inner, pos: 0 };
+    let cfg = parser.expr().ok_or_else(invalid)?;
+    parser.skip_ws();
+
+    if parser.pos != inner.len() {
+        return Err(invalid());
+    }
+
+    Ok(cfg)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn skip_ws(&mut self) {
+        while let Some(c) = self.input[self.pos..].chars().next().filter(|c| c.is_whitespace()) {
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_ws();
+        if self.input[self.pos..].starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn ident(&mut self) -> Option<String> {
+        self.skip_ws();
+        let rest = &self.input[self.pos..];
+        let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+        if len == 0 {
+            return None;
+        }
+        self.pos += len;
+        Some(rest[..len].to_string())
+    }
+
+    fn string(&mut self) -> Option<String> {
+        if !self.eat('"') {
+            return None;
+        }
+        let rest = &self.input[self.pos..];
+        let end = rest.find('"')?;
+        self.pos += end + 1;
+        Some(rest[..end].to_string())
+    }
+
+    fn list(&mut self) -> Option<Vec<Cfg>> {
+        let mut items = Vec::new();
+        if self.eat(')') {
+            return Some(items);
+        }
+        loop {
+            items.push(self.expr()?);
+            if self.eat(')') {
+                return Some(items);
+            }
+            if !self.eat(',') {
+                return None;
+            }
+        }
+    }
+
+    fn expr(&mut self) -> Option<Cfg> {
+        let name = self.ident()?;
+
+        if self.eat('(') {
+            return match name.as_str() {
+                "all" => Some(Cfg::All(self.list()?)),
+                "any" => Some(Cfg::Any(self.list()?)),
+                "not" => {
+                    let mut list = self.list()?;
+                    (list.len() == 1).then(|| Cfg::Not(Box::new(list.remove(0))))
+                }
+                _ => None,
+            };
+        }
+
+        if self.eat('=') {
+            return Some(Cfg::Equals(name, self.string()?));
+        }
+
+        Some(Cfg::Flag(name))
+    }
+}
+
+// Слияние подходящих секций [target.'cfg(...)'.build] в [build].
+// Секции применяются в порядке ключей; compiler — активный компилятор (с учётом CC/CXX).
+pub fn apply_targets(config: &mut CrabConfig, compiler: &str) -> std::io::Result<()> {
+    if config.target.is_empty() {
+        return Ok(());
+    }
+
+    let ctx = CfgContext::new(compiler, config.settings.target.as_deref());
+
+    for (key, section) in &config.target {
+        if ctx.matches(&parse_cfg(key)?)? {
+            config.build.merge(&section.build);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_nested_expressions() {
+        let cfg = parse_cfg(r#"cfg(all(unix, not(compiler = "gcc")))"#).unwrap();
+        assert_eq!(
+            cfg,
+            Cfg::All(vec![
+                Cfg::Flag("unix".to_string()),
+                Cfg::Not(Box::new(Cfg::Equals("compiler".to_string(), "gcc".to_string()))),
+            ])
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_keys() {
+        assert!(parse_cfg("unix").is_err());
+        assert!(parse_cfg("cfg(compiler = clang)").is_err());
+        assert!(parse_cfg("cfg(not(unix, windows))").is_err());
+        assert!(parse_cfg("cfg(unix) extra").is_err());
+    }
+
+    #[test]
+    fn skips_multibyte_whitespace() {
+        // неразрывный пробел (U+00A0) — два байта в UTF-8
+        let cfg = parse_cfg("cfg(all(unix,\u{a0}compiler\u{a0}=\u{a0}\"gcc\"))").unwrap();
+        assert_eq!(
+            cfg,
+            Cfg::All(vec![Cfg::Flag("unix".to_string()), Cfg::Equals("compiler".to_string(), "gcc".to_string())])
+        );
+    }
+
+    #[test]
+    fn compiler_family_matches_by_name_and_path() {
+        let ctx = CfgContext::new("/usr/bin/clang++-17", None);
+        assert!(ctx.matches(&parse_cfg(r#"cfg(compiler = "clang")"#).unwrap()).unwrap());
+        assert!(!ctx.matches(&parse_cfg(r#"cfg(compiler = "gcc")"#).unwrap()).unwrap());
+
+        let ctx = CfgContext::new("g++", None);
+        assert!(ctx.matches(&parse_cfg(r#"cfg(compiler = "gcc")"#).unwrap()).unwrap());
+        assert!(ctx.matches(&parse_cfg(r#"cfg(compiler = "g++")"#).unwrap()).unwrap());
+    }
+
+    #[test]
+    fn cross_target_comes_from_setting_or_compiler_prefix() {
+        let ctx = CfgContext::new("x86_64-w64-mingw32-g++", None);
+        assert_eq!(ctx.target, "x86_64-w64-mingw32");
+        assert!(ctx.matches(&parse_cfg("cfg(windows)").unwrap()).unwrap());
+        assert!(ctx.matches(&parse_cfg(r#"cfg(all(compiler = "gcc", os = "windows"))"#).unwrap()).unwrap());
+
+        let ctx = CfgContext::new("clang", Some("aarch64-unknown-linux-gnu"));
+        assert_eq!(ctx.os, "linux");
+        assert!(ctx.matches(&parse_cfg("cfg(unix)").unwrap()).unwrap());
+    }
+
+    #[test]
+    fn unknown_keys_are_errors() {
+        let ctx = CfgContext::new("gcc", None);
+        assert!(ctx.matches(&parse_cfg(r#"cfg(arch = "x86")"#).unwrap()).is_err());
+        assert!(ctx.matches(&parse_cfg("cfg(linux)").unwrap()).is_err());
+    }
+}
@@ -0,0 +1,137 @@
+use super::schema::CrabConfig;
+use super::extends::load_layered;
+use super::cfg::apply_targets;
+use super::interp::interpolate_config;
+use super::paths::CONFIG;
+
+// Стандартные переменные окружения тулчейна (CC, CXX, CPPFLAGS, CFLAGS, CXXFLAGS, LDFLAGS)
+// и CRAB_LAUNCHER. Приоритет: CC/CXX заменяют settings.compiler, CRAB_LAUNCHER — [build] launcher,
+// флаги из окружения добавляются после флагов [build] и потому перекрывают их (побеждает последний флаг).
+#[derive(Debug, Default)]
+pub struct ToolchainEnv {
+    pub cc: Option<String>,
+    pub cxx: Option<String>,
+    pub cppflags: Vec<String>,
+    pub cflags: Vec<String>,
+    pub cxxflags: Vec<String>,
+    pub ldflags: Vec<String>,
+    pub launcher: Option<String>,
+}
+
+impl ToolchainEnv {
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    // Разбор через произвольный источник значений (для тестов без изменения окружения)
+    pub(crate) fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        // Пустая переменная равносильна отсутствующей
+        let value = |key: &str| lookup(key).map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        let flags = |key: &str| value(key)
+            .map(|v| v.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_default();
+
+        Self {
+            cc: value("CC"),
+            cxx: value("CXX"),
+            cppflags: flags("CPPFLAGS"),
+            cflags: flags("CFLAGS"),
+            cxxflags: flags("CXXFLAGS"),
+            ldflags: flags("LDFLAGS"),
+            launcher: value("CRAB_LAUNCHER"),
+        }
+    }
+
+    // Компилятор из окружения для языка проекта: CC для C, CXX для C++
+    pub fn compiler(&self, lang: &str) -> Option<&str> {
+        if lang == "c" { self.cc.as_deref() } else { self.cxx.as_deref() }
+    }
+
+    // Флаги компиляции: CPPFLAGS + CFLAGS (C) или CXXFLAGS (C++)
+    pub fn compile_args(&self, lang: &str) -> Vec<String> {
+        let mut args = self.cppflags.clone();
+        if lang == "c" {
+            args.extend(self.cflags.iter().cloned());
+        } else {
+            args.extend(self.cxxflags.iter().cloned());
+        }
+        args
+    }
+
+    // Флаги линковки: LDFLAGS
+    pub fn link_args(&self) -> Vec<String> {
+        self.ldflags.clone()
+    }
+
+    // Наложение окружения на загруженный конфиг
+    pub fn apply(&self, config: &mut CrabConfig) {
+        let lang = config.settings.lang.clone();
+
+        if let Some(compiler) = self.compiler(&lang) {
+            config.settings.compiler = compiler.to_string();
+        }
+        if let Some(launcher) = &self.launcher {
+            config.build.launcher = launcher.clone();
+        }
+        config.build.cflags.extend(self.compile_args(&lang));
+        config.build.ldflags.extend(self.link_args());
+    }
+}
+
+// Конфиг, которым пользуется сборка: config.toml, слитый с цепочкой extends,
+// с подходящими секциями [target.'cfg(...)'], подставленными ${VAR} и
+// переменными окружения тулчейна. Только для чтения — для правки
+// config.toml используйте load_config/save_config.
+pub fn load_effective_for(profile: &str) -> std::io::Result<CrabConfig> {
+    let layered = load_layered(CONFIG.config_file)?;
+    let mut config: CrabConfig = layered.table.try_into()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    let env = ToolchainEnv::from_env();
+
+    // Условия cfg(compiler = ...) проверяются по активному компилятору, с учётом CC/CXX
+    let compiler = env.compiler(&config.settings.lang).unwrap_or(&config.settings.compiler).to_string();
+    apply_targets(&mut config, &compiler)?;
+
+    interpolate_config(&mut config, profile)?;
+    env.apply(&mut config);
+    Ok(config)
+}
+
+// То же вне конкретного профиля сборки (CRAB_PROFILE = debug)
+pub fn load_effective() -> std::io::Result<CrabConfig> {
+    load_effective_for(CONFIG.debug_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn env(pairs: &[(&str, &str)]) -> ToolchainEnv {
+        let map: HashMap<String, String> = pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        ToolchainEnv::from_lookup(|k| map.get(k).cloned())
+    }
+
+    #[test]
+    fn picks_compiler_by_language() {
+        let e = env(&[("CC", "clang"), ("CXX", "clang++")]);
+        assert_eq!(e.compiler("c"), Some("clang"));
+        assert_eq!(e.compiler("c++"), Some("clang++"));
+    }
+
+    #[test]
+    fn empty_variables_are_ignored() {
+        let e = env(&[("CC", "  "), ("CFLAGS", ""), ("CRAB_LAUNCHER", "")]);
+        assert_eq!(e.compiler("c"), None);
+        assert_eq!(e.launcher, None);
+        assert!(e.compile_args("c").is_empty());
+    }
+
+    #[test]
+    fn compile_args_combine_cppflags_with_language_flags() {
+        let e = env(&[("CPPFLAGS", "-DNDEBUG -I/opt/inc"), ("CFLAGS", "-O3"), ("CXXFLAGS", "-O1 -fno-rtti"), ("LDFLAGS", "-L/opt/lib")]);
+        assert_eq!(e.compile_args("c"), vec!["-DNDEBUG", "-I/opt/inc", "-O3"]);
+        assert_eq!(e.compile_args("c++"), vec!["-DNDEBUG", "-I/opt/inc", "-O1", "-fno-rtti"]);
+        assert_eq!(e.link_args(), vec!["-L/opt/lib"]);
+    }
+}
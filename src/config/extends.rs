@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use toml::{Table, Value};
+
+use crate::crab_err;
+
+// Конфиг, собранный по цепочке extends: итоговая таблица и происхождение
+// каждого конечного значения (ключ через точку -> файл, откуда оно взято)
+#[derive(Debug, Default)]
+pub struct Layered {
+    pub table: Table,
+    pub origins: BTreeMap<String, String>,
+}
+
+// Загрузка файла с учётом extends = "<путь к базовому файлу>" (путь — относительно
+// файла, в котором он указан; цепочки допускаются, циклы — ошибка).
+// Таблицы сливаются рекурсивно, остальные значения (в т.ч. массивы) локальный файл заменяет целиком.
+pub fn load_layered(path: &str) -> std::io::Result<Layered> {
+    load_layer(Path::new(path), &mut Vec::new())
+}
+
+fn load_layer(path: &Path, chain: &mut Vec<PathBuf>) -> std::io::Result<Layered> {
+    let canonical = fs::canonicalize(path)
+        .map_err(|e| std::io::Error::new(e.kind(), format!("Cannot read config {}: {}", path.display(), e)))?;
+
+    if chain.contains(&canonical) {
+        crab_err!(ErrorKind::InvalidData, "Cyclic extends: {} is already in the chain", path.display());
+    }
+
+    let text = fs::read_to_string(path)?;
+    let mut table: Table = toml::from_str(&text)
+        .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))?;
+
+    chain.push(canonical);
+
+    let mut layered = match table.remove("extends") {
+        Some(Value::String(base)) => {
+            let dir = path.parent().unwrap_or(Path::new(""));
+            load_layer(&dir.join(base), chain)?
+        }
+        Some(_) => crab_err!(ErrorKind::InvalidData, "{}: 'extends' must be a path string", path.display()),
+        None => Layered::default(),
+    };
+
+    chain.pop();
+
+    merge(&mut layered.table, table, &path.display().to_string(), "", &mut layered.origins);
+    Ok(layered)
+}
+
+fn merge(base: &mut Table, local: Table, origin: &str, prefix: &str, origins: &mut BTreeMap<String, String>) {
+    for (key, value) in local {
+        let full = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(b)), Value::Table(l)) => merge(b, l, origin, &full, origins),
+            (_, value) => {
+                // значение заменяется целиком — забываем происхождение прежних вложенных ключей
+                let nested = format!("{}.", full);
+                origins.retain(|k, _| k != &full && !k.starts_with(&nested));
+                record(&value, &full, origin, origins);
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn record(value: &Value, key: &str, origin: &str, origins: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Table(t) => {
+            for (k, v) in t {
+                record(v, &format!("{}.{}", key, k), origin, origins);
+            }
+        }
+        _ => {
+            origins.insert(key.to_string(), origin.to_string());
+        }
+    }
+}
+
+// Значение по ключу через точку (для вывода crab config show)
+pub fn lookup<'a>(table: &'a Table, key: &str) -> Option<&'a Value> {
+    let mut parts = key.split('.');
+    let mut current = table.get(parts.next()?)?;
+    for part in parts {
+        current = current.as_table()?.get(part)?;
+    }
+    Some(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+    fn temp_dir(tag: &str) -> PathBuf {
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let p = std::env::temp_dir().join(format!("crab_test_{}_{}_{}", tag, nanos, n));
+        fs::create_dir_all(&p).unwrap();
+        p
+    }
+
+    #[test]
+    fn chained_extends_merge_with_local_overrides() {
+        let dir = temp_dir("extends");
+        fs::create_dir_all(dir.join("common")).unwrap();
+        fs::create_dir_all(dir.join("app")).unwrap();
+
+        fs::write(dir.join("common/root.toml"), "[build]\nstandard = \"c++17\"\ncflags = [\"-Wall\"]\n").unwrap();
+        fs::write(dir.join("common/base.toml"), "extends = \"root.toml\"\n[build]\ndefines = [\"BASE\"]\n").unwrap();
+        fs::write(dir.join("app/config.toml"), "extends = \"../common/base.toml\"\n[build]\nstandard = \"c++20\"\n").unwrap();
+
+        let layered = load_layered(&dir.join("app/config.toml").display().to_string()).unwrap();
+        let build = layered.table["build"].as_table().unwrap();
+
+        assert_eq!(build["standard"].as_str(), Some("c++20"));
+        assert_eq!(build["defines"].as_array().unwrap().len(), 1);
+        assert!(!layered.table.contains_key("extends"));
+
+        assert!(layered.origins["build.standard"].ends_with("config.toml"));
+        assert!(layered.origins["build.defines"].ends_with("base.toml"));
+        assert!(layered.origins["build.cflags"].ends_with("root.toml"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn cyclic_extends_is_an_error() {
+        let dir = temp_dir("extends_cycle");
+        fs::write(dir.join("a.toml"), "extends = \"b.toml\"\n").unwrap();
+        fs::write(dir.join("b.toml"), "extends = \"a.toml\"\n").unwrap();
+
+        let err = load_layered(&dir.join("a.toml").display().to_string()).unwrap_err();
+        assert!(err.to_string().contains("Cyclic"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn lookup_follows_dotted_keys() {
+        let table: Table = toml::from_str("[build]\nstandard = \"c11\"\n").unwrap();
+        assert_eq!(lookup(&table, "build.standard").and_then(Value::as_str), Some("c11"));
+        assert!(lookup(&table, "build.nope").is_none());
+    }
+}
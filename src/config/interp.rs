@@ -0,0 +1,225 @@
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use super::paths::CONFIG;
+use super::schema::CrabConfig;
+use crate::crab_err;
+
+// Подстановка переменных в строку: ${VAR}, ${VAR:-default} (default — если VAR
+// не задана или пуста), "$$" — литеральный "$". Неизвестная переменная — ошибка.
+pub fn interpolate(input: &str, lookup: &dyn Fn(&str) -> Option<String>) -> std::io::Result<String> {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let tail = &rest[pos + 1..];
+
+        if let Some(after) = tail.strip_prefix('$') {
+            out.push('$');
+            rest = after;
+            continue;
+        }
+
+        let Some(body_start) = tail.strip_prefix('{') else {
+            // одиночный "$" без скобок оставляем как есть
+            out.push('$');
+            rest = tail;
+            continue;
+        };
+
+        let Some(end) = body_start.find('}') else {
+            crab_err!(ErrorKind::InvalidData, "Unterminated '${{' in config value: {}", input);
+        };
+
+        let body = &body_start[..end];
+        let (name, default) = match body.split_once(":-") {
+            Some((n, d)) => (n, Some(d)),
+            None => (body, None),
+        };
+
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            crab_err!(ErrorKind::InvalidData, "Invalid variable name '{}' in config value: {}", name, input);
+        }
+
+        // пустое значение заменяется умолчанием только в форме ${VAR:-default}
+        match (lookup(name), default) {
+            (Some(value), Some(d)) if value.is_empty() => out.push_str(d),
+            (Some(value), _) => out.push_str(&value),
+            (None, Some(d)) => out.push_str(d),
+            (None, None) => {
+                crab_err!(ErrorKind::NotFound, "Undefined variable '{}' in config value: {}", name, input);
+            }
+        }
+
+        rest = &body_start[end + 1..];
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+// Встроенные переменные: корень проекта, профиль и каталог его артефактов
+fn builtin(name: &str, profile: &str) -> Option<String> {
+    let root = || std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+
+    match name {
+        "CRAB_PROJECT_ROOT" => Some(root().display().to_string()),
+        "CRAB_PROFILE" => Some(profile.to_string()),
+        "CRAB_TARGET_DIR" => Some(root().join(CONFIG.build_dir).join(profile).display().to_string()),
+        _ => None,
+    }
+}
+
+fn interpolate_all(values: &mut [String], lookup: &dyn Fn(&str) -> Option<String>) -> std::io::Result<()> {
+    for v in values.iter_mut() {
+        *v = interpolate(v, lookup)?;
+    }
+    Ok(())
+}
+
+// Подстановка во все строковые поля [build], [libraries], [library], [[bin]] и [module.*].
+// Встроенные переменные CRAB_* имеют приоритет над окружением.
+pub fn interpolate_config(config: &mut CrabConfig, profile: &str) -> std::io::Result<()> {
+    let lookup = |name: &str| builtin(name, profile).or_else(|| std::env::var(name).ok());
+
+    let build = &mut config.build;
+    build.standard = interpolate(&build.standard, &lookup)?;
+    build.pch = interpolate(&build.pch, &lookup)?;
+    build.launcher = interpolate(&build.launcher, &lookup)?;
+    interpolate_all(&mut build.defines, &lookup)?;
+    interpolate_all(&mut build.include_dirs, &lookup)?;
+    interpolate_all(&mut build.cflags, &lookup)?;
+    interpolate_all(&mut build.ldflags, &lookup)?;
+    for o in build.overrides.iter_mut() {
+        interpolate_all(&mut o.defines, &lookup)?;
+        interpolate_all(&mut o.include_dirs, &lookup)?;
+        interpolate_all(&mut o.cflags, &lookup)?;
+    }
+
+    interpolate_all(&mut config.libraries.path, &lookup)?;
+
+    let library = &mut config.library;
+    for file in [&mut library.version_script, &mut library.exports].into_iter().flatten() {
+        *file = interpolate(file, &lookup)?;
+    }
+
+    for bin in config.bins.iter_mut() {
+        interpolate_all(&mut bin.sources, &lookup)?;
+        interpolate_all(&mut bin.defines, &lookup)?;
+        interpolate_all(&mut bin.include_dirs, &lookup)?;
+        interpolate_all(&mut bin.cflags, &lookup)?;
+        interpolate_all(&mut bin.ldflags, &lookup)?;
+    }
+
+    for module in config.module.values_mut() {
+        module.path = interpolate(&module.path, &lookup)?;
+        interpolate_all(&mut module.dependencies, &lookup)?;
+        interpolate_all(&mut module.defines, &lookup)?;
+        interpolate_all(&mut module.cflags, &lookup)?;
+        interpolate_all(&mut module.ldflags, &lookup)?;
+        if let Some(name) = module.output_name.as_mut() {
+            *name = interpolate(name, &lookup)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(name: &str) -> Option<String> {
+        match name {
+            "VENDOR_ROOT" => Some("/opt/vendor".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn substitutes_variables() {
+        assert_eq!(interpolate("${VENDOR_ROOT}/include", &vars).unwrap(), "/opt/vendor/include");
+        assert_eq!(interpolate("-I${VENDOR_ROOT} -L${VENDOR_ROOT}/lib", &vars).unwrap(), "-I/opt/vendor -L/opt/vendor/lib");
+    }
+
+    #[test]
+    fn uses_default_for_unset_or_empty() {
+        assert_eq!(interpolate("${SDK:-/usr}/lib", &vars).unwrap(), "/usr/lib");
+        assert_eq!(interpolate("${EMPTY:-x}", &vars).unwrap(), "x");
+        assert_eq!(interpolate("${VENDOR_ROOT:-x}", &vars).unwrap(), "/opt/vendor");
+    }
+
+    #[test]
+    fn undefined_variable_is_an_error() {
+        let err = interpolate("${NOPE}/lib", &vars).unwrap_err();
+        assert!(err.to_string().contains("NOPE"));
+    }
+
+    #[test]
+    fn empty_variable_without_default_is_substituted() {
+        assert_eq!(interpolate("-L${EMPTY}/lib", &vars).unwrap(), "-L/lib");
+    }
+
+    #[test]
+    fn escapes_and_plain_dollars_are_kept() {
+        assert_eq!(interpolate("$${HOME}", &vars).unwrap(), "${HOME}");
+        assert_eq!(interpolate("-Wl,-rpath,$ORIGIN", &vars).unwrap(), "-Wl,-rpath,$ORIGIN");
+    }
+
+    // Конфиг из текста; ${CRAB_PROFILE} — встроенная переменная, от окружения не зависит
+    fn interpolated(extra: &str) -> CrabConfig {
+        let text = format!(
+            "[project]\nname = \"p\"\nversion = \"0.1.0\"\ncreated = 2024\n\
+             [settings]\nlang = \"c++\"\ncompiler = \"g++\"\nsource_dir = \"src\"\nheader_dir = \"include\"\n{}",
+            extra
+        );
+        let mut config: CrabConfig = toml::from_str(&text).unwrap();
+        interpolate_config(&mut config, "release").unwrap();
+        config
+    }
+
+    #[test]
+    fn interpolates_build_pch_and_launcher() {
+        let config = interpolated("[build]\npch = \"include/${CRAB_PROFILE}.hpp\"\nlauncher = \"${CRAB_LAUNCHER_TEST:-ccache}\"\n");
+        assert_eq!(config.build.pch, "include/release.hpp");
+        assert_eq!(config.build.launcher, "ccache");
+    }
+
+    #[test]
+    fn interpolates_module_flags() {
+        let config = interpolated("[module.net]\npath = \"net\"\ndefines = [\"MODE=${CRAB_PROFILE}\"]\n\
+            cflags = [\"-I${CRAB_PROFILE}/inc\"]\nldflags = [\"-L${CRAB_PROFILE}/lib\"]\n");
+        let module = &config.module["net"];
+        assert_eq!(module.defines, vec!["MODE=release"]);
+        assert_eq!(module.cflags, vec!["-Irelease/inc"]);
+        assert_eq!(module.ldflags, vec!["-Lrelease/lib"]);
+    }
+
+    #[test]
+    fn interpolates_bin_targets() {
+        let config = interpolated("[[bin]]\nname = \"tool\"\nsources = [\"src/${CRAB_PROFILE}/*.cpp\"]\n\
+            defines = [\"P=${CRAB_PROFILE}\"]\ninclude_dirs = [\"${CRAB_PROFILE}\"]\n\
+            cflags = [\"-DC=${CRAB_PROFILE}\"]\nldflags = [\"-L${CRAB_PROFILE}\"]\n");
+        let bin = &config.bins[0];
+        assert_eq!(bin.sources, vec!["src/release/*.cpp"]);
+        assert_eq!(bin.defines, vec!["P=release"]);
+        assert_eq!(bin.include_dirs, vec!["release"]);
+        assert_eq!(bin.cflags, vec!["-DC=release"]);
+        assert_eq!(bin.ldflags, vec!["-Lrelease"]);
+    }
+
+    #[test]
+    fn interpolates_library_export_files() {
+        let config = interpolated("[library]\nkind = \"dynamic\"\nversion_script = \"${CRAB_PROFILE}.map\"\nexports = \"${CRAB_PROFILE}.sym\"\n");
+        assert_eq!(config.library.version_script.as_deref(), Some("release.map"));
+        assert_eq!(config.library.exports.as_deref(), Some("release.sym"));
+    }
+
+    #[test]
+    fn malformed_references_are_errors() {
+        assert!(interpolate("${VENDOR_ROOT", &vars).is_err());
+        assert!(interpolate("${A-B}", &vars).is_err());
+    }
+}
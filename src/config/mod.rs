@@ -1,7 +1,12 @@
 pub mod paths;
 pub mod schema;
 pub mod update;
+pub mod env;
+pub mod interp;
+pub mod cfg;
+pub mod extends;
 
 pub use paths::CONFIG;
-pub use schema::{load_config, save_config, Build, Changed, CrabConfig, Libraries, Module, Project, Settings, TestConfig};
+pub use schema::{add_files, load_config, remove_modules, save_config, save_module, sync_module_sources, AbiDump, AbiSymbol, BinTarget, Build, Changed, CrabConfig, FileOverride, InstallManifest, InstalledFile, Libraries, Library, Module, Project, Settings, TargetSection, TestConfig, Unity};
 pub use update::CrabUpdateINI;
+pub use env::{load_effective, load_effective_for, ToolchainEnv};
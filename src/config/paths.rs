@@ -11,11 +11,18 @@ pub struct Config {
     pub binary_dir: &'static str,
     pub include_file: &'static str,
     pub lib_file: &'static str,
+    pub pkg_file: &'static str,
+    pub libraries_file: &'static str,
     pub object_data: &'static str,
     pub version: &'static str,
     pub dependencies: &'static str,
     pub module_dir: &'static str,
+    pub target_dir: &'static str,
+    pub pch_dir: &'static str,
+    pub unity_dir: &'static str,
+    pub bmi_dir: &'static str,
     pub log: &'static str,
+    pub abi_file: &'static str,
 }
 
 pub const CONFIG: Config = Config {
@@ -31,9 +38,16 @@ pub const CONFIG: Config = Config {
     binary_dir: "bin",
     include_file: "Include.include.crb",
     lib_file: "Lib.lib.crb",
+    pkg_file: "Pkg.pkg.crb",
+    libraries_file: "Libraries.libraries.crb",
     object_data: "object_data.o.crb",
     version: "v0.0.1",
     dependencies: "dependencies.d.crb",
     module_dir: "module",
+    target_dir: "target",
+    pch_dir: "pch",
+    unity_dir: "unity",
+    bmi_dir: "bmi",
     log: "crab.log",
+    abi_file: "abi.toml",
 };
@@ -1,36 +1,99 @@
-use std::{fs::File, io::{Read, Write}};
+use std::{fs::File, io::{Read, Write}, path::Path};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::glob::{glob_match, is_glob};
 
 /*=====ОСНОВНОЙ КОНИФГ=====*/
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct CrabConfig {
+    // Базовый конфиг, от которого наследуются значения (путь относительно этого файла)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
     pub project: Project,
     pub settings: Settings,
     #[serde(default)]
     pub build: Build,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "TestConfig::is_default")]
     pub test: TestConfig,
     #[serde(default)]
     pub files: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Libraries::is_empty")]
     pub libraries: Libraries,
+    #[serde(default, skip_serializing_if = "Library::is_default")]
+    pub library: Library,
+    // Исполняемые цели [[bin]], линкуемые с библиотекой проекта
+    #[serde(default, rename = "bin", skip_serializing_if = "Vec::is_empty")]
+    pub bins: Vec<BinTarget>,
+    #[serde(default)]
     pub module: HashMap<String, Module>,
+    // Условные секции [target.'cfg(...)'.build], сливаемые в [build] при совпадении
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub target: BTreeMap<String, TargetSection>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+pub struct TargetSection {
+    #[serde(default)]
+    pub build: Build,
 }
 
 // Пользовательские параметры сборки (секция [build] в config.toml).
 // Все поля опциональны и добавляются поверх встроенных флагов профиля.
-#[derive(Deserialize, Serialize, Debug, Default)]
+// Пустые поля не записываются, чтобы не перекрывать значения из extends.
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
 pub struct Build {
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub standard: String,          // стандарт языка, напр. "c++17" / "c11" -> -std=...
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub defines: Vec<String>,      // макросы -D, напр. "DEBUG" / "VER=2"
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub include_dirs: Vec<String>, // дополнительные каталоги заголовков -I
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub cflags: Vec<String>,       // произвольные флаги компиляции
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub ldflags: Vec<String>,      // произвольные флаги линковки
+    #[serde(default, rename = "override", skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<FileOverride>, // флаги для отдельных файлов ([[build.override]])
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub pch: String,               // предкомпилированный заголовок, напр. "include/pch.hpp"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unity: Option<Unity>,      // unity-сборка: unity = { batch = 8, exclude = ["src/legacy/**"] }
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub launcher: String,          // обёртка компиляции, напр. "ccache" / "sccache"
+}
+
+// Объединение исходников пачками по batch в один файл компиляции.
+// Файлы из exclude (и с флагами [[build.override]]) компилируются отдельно.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Unity {
+    #[serde(default = "default_unity_batch")]
+    pub batch: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+}
+
+impl Default for Unity {
+    fn default() -> Self {
+        Self { batch: default_unity_batch(), exclude: Vec::new() }
+    }
+}
+
+fn default_unity_batch() -> usize {
+    8
+}
+
+// Флаги компиляции для файлов, подходящих под glob-шаблон, напр.
+// [[build.override]] pattern = "src/third_party/**" cflags = ["-w"]
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+pub struct FileOverride {
+    pub pattern: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub defines: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include_dirs: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cflags: Vec<String>,
 }
 
 impl Build {
@@ -52,44 +115,220 @@ impl Build {
         args
     }
 
+    // Дополнительные аргументы для конкретного файла из подходящих [[build.override]].
+    // Идут после общих флагов, поэтому перекрывают их (напр. -O3 или -w для одного файла).
+    pub fn file_args(&self, file: &str) -> Vec<String> {
+        let mut args = Vec::new();
+
+        for o in self.overrides.iter().filter(|o| glob_match(&o.pattern, file)) {
+            for d in &o.defines {
+                args.push(format!("-D{}", d));
+            }
+            for inc in &o.include_dirs {
+                args.push(format!("-I{}", inc));
+            }
+            args.extend(o.cflags.iter().cloned());
+        }
+
+        args
+    }
+
     // Аргументы, добавляемые на этапе линковки
     pub fn link_args(&self) -> Vec<String> {
         self.ldflags.clone()
     }
+
+    // Наложение другой секции: непустые standard, pch, launcher и unity заменяют текущие, списки дополняются
+    pub fn merge(&mut self, other: &Build) {
+        if !other.standard.trim().is_empty() {
+            self.standard = other.standard.clone();
+        }
+        if !other.pch.trim().is_empty() {
+            self.pch = other.pch.clone();
+        }
+        if !other.launcher.trim().is_empty() {
+            self.launcher = other.launcher.clone();
+        }
+        if other.unity.is_some() {
+            self.unity = other.unity.clone();
+        }
+        self.defines.extend(other.defines.iter().cloned());
+        self.include_dirs.extend(other.include_dirs.iter().cloned());
+        self.cflags.extend(other.cflags.iter().cloned());
+        self.ldflags.extend(other.ldflags.iter().cloned());
+        self.overrides.extend(other.overrides.iter().cloned());
+    }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Project {
     pub name: String,
     pub version: String,
     pub created: i32,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Settings {
     pub lang: String,  // "c" или "c++"
     pub compiler: String,
     pub source_dir: String,
     pub header_dir: String,
+    // Цель кросс-сборки (триплет), учитывается в условиях cfg(...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
 }
 
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
 pub struct Libraries {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub path: Vec<String>,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+impl Libraries {
+    // Пустая секция не записывается, чтобы не перекрывать [libraries] из extends
+    pub fn is_empty(&self) -> bool {
+        self.path.is_empty()
+    }
+}
+
+// Секция [library]: параметры библиотеки, собираемой из проекта (crab build lib)
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+pub struct Library {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>, // "static" | "dynamic" — библиотека как цель `crab build`; "header-only" — только заголовки
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>, // имя без префикса/расширения: "foo" -> libfoo.a (по умолчанию project.name)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub link_deps: bool,      // линковать динамическую библиотеку с найденными сторонними библиотеками
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<String>,     // "hidden" -> -fvisibility=hidden, наружу только помеченное <NAME>_EXPORT
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_script: Option<String>, // скрипт версий линковщика (GNU ld: --version-script)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exports: Option<String>,        // файл со списком экспортируемых символов (по одному в строке)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub public_headers: Vec<String>,    // glob-шаблоны устанавливаемых заголовков (по умолчанию весь header_dir)
+}
+
+impl Library {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    // Имя библиотеки: [library].name или имя проекта
+    pub fn output_name(&self, project: &str) -> String {
+        self.name.as_deref().map(str::trim).filter(|n| !n.is_empty()).unwrap_or(project).to_string()
+    }
+
+    pub fn is_header_only(&self) -> bool {
+        self.kind.as_deref() == Some("header-only")
+    }
+}
+
+// Исполняемая цель [[bin]]: name = "cli", sources = ["src/cli/*.cpp"].
+// Её исходники не входят в библиотеку проекта, а сама она линкуется с библиотекой, если та есть.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct BinTarget {
+    pub name: String,
+    pub sources: Vec<String>,      // пути или glob-шаблоны относительно корня проекта
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub default: bool,             // цель для `crab build` / `crab run` без --bin
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub defines: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include_dirs: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cflags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ldflags: Vec<String>,
+}
+
+impl BinTarget {
+    // Подходит ли исходник под sources цели
+    pub fn has_source(&self, file: &str) -> bool {
+        let file = file.trim_start_matches("./");
+        self.sources.iter().any(|s| if is_glob(s) { glob_match(s, file) } else { s.trim_start_matches("./") == file })
+    }
+
+    // Флаги цели в виде секции [build] — накладываются поверх общей через Build::merge
+    pub fn build_overlay(&self) -> Build {
+        Build {
+            defines: self.defines.clone(),
+            include_dirs: self.include_dirs.clone(),
+            cflags: self.cflags.clone(),
+            ldflags: self.ldflags.clone(),
+            ..Build::default()
+        }
+    }
+}
+
+impl CrabConfig {
+    // Принадлежит ли исходник какой-либо цели [[bin]]
+    pub fn is_bin_source(&self, file: &str) -> bool {
+        self.bins.iter().any(|b| b.has_source(file))
+    }
+
+    // Лежит ли исходник в каталоге какого-либо модуля
+    pub fn is_module_source(&self, file: &str) -> bool {
+        let file = std::path::Path::new(file.trim_start_matches("./"));
+        self.module.values().any(|m| file.starts_with(m.path.trim_start_matches("./")))
+    }
+
+    // Цель по умолчанию: помеченная default = true, иначе первая
+    pub fn default_bin(&self) -> Option<&BinTarget> {
+        self.bins.iter().find(|b| b.default).or(self.bins.first())
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
 pub struct Module {
     pub path: String,
     #[serde(default)]
     pub dependencies: Vec<String>,
     #[serde(default)]
     pub output_name: Option<String>,
+    // Модули, с которыми линкуется этот; "core" — статическая библиотека основного проекта
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub uses: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub defines: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cflags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ldflags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,      // "c" | "c++", по умолчанию язык проекта
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub standard: String,          // стандарт модуля; при другом lang стандарт проекта не наследуется
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,      // "bin" (по умолчанию) | "static" | "shared"
+}
+
+impl Module {
+    pub fn kind(&self) -> &str {
+        self.kind.as_deref().unwrap_or("bin")
+    }
+
+    // Имя результата: output_name или имя модуля
+    pub fn bin_name(&self, name: &str) -> String {
+        self.output_name.as_deref().map(str::trim).filter(|n| !n.is_empty()).unwrap_or(name).to_string()
+    }
+
+    // Флаги модуля в виде секции [build] — накладываются поверх общей через Build::merge
+    pub fn build_overlay(&self) -> Build {
+        Build {
+            standard: self.standard.clone(),
+            defines: self.defines.clone(),
+            cflags: self.cflags.clone(),
+            ldflags: self.ldflags.clone(),
+            ..Build::default()
+        }
+    }
 }
 
 // Секция [test] в config.toml (опциональна, по умолчанию ищет тесты в tests/)
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct TestConfig {
     #[serde(default = "default_test_dir")]
     pub dir: String,
@@ -105,12 +344,58 @@ impl Default for TestConfig {
     }
 }
 
+impl TestConfig {
+    fn is_default(&self) -> bool {
+        self.dir == default_test_dir()
+    }
+}
+
 /*=====ДОП КОНИФГ=====*/
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Changed {
     pub files: HashMap<String, String>,
+    // Компилятор и флаги прошлой сборки: при их изменении пересобирается всё
+    #[serde(default)]
+    pub signature: String,
+}
+
+// Снимок экспортируемых символов динамической библиотеки (crab abi dump, abi.toml).
+// Хранится в репозитории и сравнивается с текущей сборкой в crab abi check.
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+pub struct AbiDump {
+    pub library: String,
+    pub version: String,
+    #[serde(default)]
+    pub symbols: BTreeMap<String, AbiSymbol>,
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct AbiSymbol {
+    pub kind: String, // тип из nm: T — код, D/B/R — данные, W/V — слабые, i — ifunc
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+// Манифест установки (crab install): что и куда скопировано, для crab uninstall.
+// Хранится в каталоге данных пользователя, по одному файлу на проект и каталог установки.
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+pub struct InstallManifest {
+    pub project: String,
+    pub version: String,
+    pub prefix: String,
+    pub installed: String,
+    #[serde(default)]
+    pub files: Vec<InstalledFile>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct InstalledFile {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub checksum: String, // FNV-1a 64 содержимого (пусто для ссылок)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>, // цель символической ссылки
+}
 
 pub fn load_config<T: DeserializeOwned>(path: &str) -> std::io::Result<T> {
     let mut file = File::open(path)?;
@@ -127,6 +412,86 @@ pub fn save_config<T: Serialize>(config: &T, path: &str) -> std::io::Result<()>
     Ok(())
 }
 
+// Регистрация исходников в [files] со значением "on". Меняется только эта таблица:
+// остальной документ записывается как прочитан, без значений по умолчанию, которые
+// перекрыли бы унаследованные через extends. true — что-то добавлено и файл записан.
+pub fn add_files(path: &str, files: &[String]) -> std::io::Result<bool> {
+    let mut doc: toml::Table = load_config(path)?;
+
+    if insert_files(&mut doc, files, path)? {
+        save_config(&doc, path)?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+// Запись модуля в [module.<name>]; остальной документ не меняется
+pub fn save_module(path: &str, name: &str, module: &Module) -> std::io::Result<()> {
+    let mut doc: toml::Table = load_config(path)?;
+    let value = toml::Value::try_from(module).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+    subtable(&mut doc, "module", path)?.insert(name.to_string(), value);
+    save_config(&doc, path)
+}
+
+// Исходники модуля перед сборкой: новые — в [files], весь список — в dependencies,
+// если модуль объявлен в этом файле (а не унаследован через extends)
+pub fn sync_module_sources(path: &str, name: &str, files: &[String]) -> std::io::Result<()> {
+    let mut doc: toml::Table = load_config(path)?;
+    insert_files(&mut doc, files, path)?;
+
+    if let Some(module) = doc.get_mut("module").and_then(|m| m.get_mut(name)).and_then(|m| m.as_table_mut()) {
+        module.insert("dependencies".to_string(), toml::Value::Array(files.iter().cloned().map(toml::Value::String).collect()));
+    }
+
+    save_config(&doc, path)
+}
+
+// Удаление модулей из [module]; если указан каталог модуля — и его исходников из [files].
+// Возвращает модули, которых в этом файле нет: они унаследованы через extends.
+pub fn remove_modules(path: &str, modules: &[(String, Option<String>)]) -> std::io::Result<Vec<String>> {
+    let mut doc: toml::Table = load_config(path)?;
+    let mut inherited = Vec::new();
+
+    for (name, dir) in modules {
+        if subtable(&mut doc, "module", path)?.remove(name).is_none() {
+            inherited.push(name.clone());
+            continue;
+        }
+
+        if let Some(dir) = dir {
+            let dir = Path::new(dir.trim_start_matches("./"));
+            subtable(&mut doc, "files", path)?.retain(|f, _| !Path::new(f.trim_start_matches("./")).starts_with(dir));
+        }
+    }
+
+    if inherited.len() < modules.len() {
+        save_config(&doc, path)?;
+    }
+    Ok(inherited)
+}
+
+fn insert_files(doc: &mut toml::Table, files: &[String], path: &str) -> std::io::Result<bool> {
+    let table = subtable(doc, "files", path)?;
+
+    let mut added = false;
+    for f in files {
+        if !table.contains_key(f) {
+            table.insert(f.clone(), toml::Value::String("on".to_string()));
+            added = true;
+        }
+    }
+    Ok(added)
+}
+
+// Таблица верхнего уровня (создаётся, если её нет)
+fn subtable<'a>(doc: &'a mut toml::Table, key: &str, path: &str) -> std::io::Result<&'a mut toml::Table> {
+    match doc.entry(key).or_insert_with(|| toml::Value::Table(toml::Table::new())).as_table_mut() {
+        Some(table) => Ok(table),
+        None => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{}: [{}] must be a table", path, key))),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -146,6 +511,7 @@ mod tests {
             include_dirs: vec!["third_party/include".to_string()],
             cflags: vec!["-Wpedantic".to_string()],
             ldflags: vec!["-lpthread".to_string()],
+            ..Default::default()
         };
 
         assert_eq!(
@@ -155,6 +521,54 @@ mod tests {
         assert_eq!(b.link_args(), vec!["-lpthread"]);
     }
 
+    #[test]
+    fn merge_appends_lists_and_overrides_standard() {
+        let mut b = Build {
+            standard: "c++17".to_string(),
+            defines: vec!["A".to_string()],
+            ..Default::default()
+        };
+        b.merge(&Build {
+            standard: "c++20".to_string(),
+            defines: vec!["B".to_string()],
+            ldflags: vec!["-fuse-ld=lld".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(b.standard, "c++20");
+        assert_eq!(b.defines, vec!["A", "B"]);
+        assert_eq!(b.link_args(), vec!["-fuse-ld=lld"]);
+
+        b.merge(&Build::default());
+        assert_eq!(b.standard, "c++20");
+    }
+
+    #[test]
+    fn file_args_apply_matching_overrides_in_order() {
+        let b = Build {
+            cflags: vec!["-Wall".to_string()],
+            overrides: vec![
+                FileOverride {
+                    pattern: "src/third_party/**".to_string(),
+                    cflags: vec!["-w".to_string()],
+                    ..Default::default()
+                },
+                FileOverride {
+                    pattern: "hot_loop.cpp".to_string(),
+                    defines: vec!["HOT".to_string()],
+                    cflags: vec!["-O3".to_string()],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(b.file_args("src/third_party/json/json.cpp"), vec!["-w"]);
+        assert_eq!(b.file_args("src/math/hot_loop.cpp"), vec!["-DHOT", "-O3"]);
+        assert!(b.file_args("src/main.cpp").is_empty());
+        // общие флаги не зависят от override
+        assert_eq!(b.compile_args(), vec!["-Wall"]);
+    }
+
     #[test]
     fn blank_standard_is_skipped() {
         let b = Build {
@@ -163,4 +577,69 @@ mod tests {
         };
         assert!(b.compile_args().is_empty());
     }
+
+    #[test]
+    fn registering_files_keeps_inherited_libraries() {
+        let dir = std::env::temp_dir().join(format!("crab_test_add_files_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("base.toml"), "[libraries]\npath = [\"/tmp/rv/sdk\"]\n").unwrap();
+        let config = dir.join("config.toml");
+        std::fs::write(&config, "extends = \"base.toml\"\n\n[project]\nname = \"p\"\nversion = \"0.1.0\"\ncreated = 2024\n\n\
+            [settings]\nlang = \"c++\"\ncompiler = \"g++\"\nsource_dir = \"src\"\nheader_dir = \"include\"\n\n\
+            [files]\n\"src/main.cpp\" = \"on\"\n").unwrap();
+        let path = config.display().to_string();
+
+        assert!(add_files(&path, &["src/main.cpp".to_string(), "src/a.cpp".to_string()]).unwrap());
+        assert!(!add_files(&path, &["src/a.cpp".to_string()]).unwrap());
+
+        let text = std::fs::read_to_string(&config).unwrap();
+        assert!(text.contains("\"src/a.cpp\" = \"on\""));
+        assert!(!text.contains("libraries"));
+
+        let layered = crate::config::extends::load_layered(&path).unwrap();
+        let effective: CrabConfig = layered.table.try_into().unwrap();
+        assert_eq!(effective.libraries.path, vec!["/tmp/rv/sdk"]);
+
+        // полная запись конфига тоже не добавляет пустую секцию
+        save_config(&load_config::<CrabConfig>(&path).unwrap(), &path).unwrap();
+        assert!(!std::fs::read_to_string(&config).unwrap().contains("libraries"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn module_edits_touch_only_local_modules() {
+        let dir = std::env::temp_dir().join(format!("crab_test_module_edit_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("base.toml"), "[module.net]\npath = \"src/net\"\n").unwrap();
+        let config = dir.join("config.toml");
+        std::fs::write(&config, "extends = \"base.toml\"\n\n[project]\nname = \"p\"\nversion = \"0.1.0\"\ncreated = 2024\n\n\
+            [settings]\nlang = \"c++\"\ncompiler = \"g++\"\nsource_dir = \"src\"\nheader_dir = \"include\"\n\n\
+            [files]\n\"src/main.cpp\" = \"on\"\n").unwrap();
+        let path = config.display().to_string();
+
+        let app = Module { path: "src/app".to_string(), output_name: Some("app".to_string()), ..Module::default() };
+        save_module(&path, "app", &app).unwrap();
+        sync_module_sources(&path, "app", &["src/app/a.cpp".to_string()]).unwrap();
+        // унаследованный модуль не копируется в локальный файл
+        sync_module_sources(&path, "net", &["src/net/n.cpp".to_string()]).unwrap();
+
+        let local: CrabConfig = load_config(&path).unwrap();
+        assert_eq!(local.module["app"].dependencies, vec!["src/app/a.cpp"]);
+        assert!(!local.module.contains_key("net"));
+        assert!(local.files.contains_key("src/app/a.cpp") && local.files.contains_key("src/net/n.cpp"));
+
+        let layered = crate::config::extends::load_layered(&path).unwrap();
+        let effective: CrabConfig = layered.table.try_into().unwrap();
+        assert_eq!(effective.module["net"].path, "src/net");
+        assert_eq!(effective.module["app"].path, "src/app");
+
+        let inherited = remove_modules(&path, &[("app".to_string(), Some("src/app".to_string())), ("net".to_string(), None)]).unwrap();
+        assert_eq!(inherited, vec!["net"]);
+        let local: CrabConfig = load_config(&path).unwrap();
+        assert!(local.module.is_empty());
+        assert!(!local.files.contains_key("src/app/a.cpp") && local.files.contains_key("src/main.cpp"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
@@ -1,4 +1,6 @@
+use super::extends::{load_layered, lookup};
 use super::schema::{load_config, save_config, CrabConfig};
+use crate::crab_print;
 
 pub struct CrabUpdateINI {
     file: String,
@@ -22,4 +24,18 @@ impl CrabUpdateINI {
         save_config(&config, &self.file)?;
         Ok(())
     }
+
+    // Итоговые значения конфига с учётом extends и файл, откуда взято каждое
+    pub fn show(&self) -> std::io::Result<()> {
+        let layered = load_layered(&self.file)?;
+        let width = layered.origins.keys().map(|k| k.len()).max().unwrap_or(0);
+
+        for (key, origin) in &layered.origins {
+            if let Some(value) = lookup(&layered.table, key) {
+                crab_print!("{:<width$} = {}  {}", key, value, crate::color::paint("2", &format!("# {}", origin)), width = width);
+            }
+        }
+
+        Ok(())
+    }
 }
@@ -1,22 +1,38 @@
 use std::collections::HashSet;
 use std::{env, fs::{self, File, OpenOptions}, io::{BufRead, BufReader, Write}, path::Path, path::PathBuf, process::{Command, Stdio}};
-use crate::config::{load_config, CrabConfig, CONFIG};
+use crate::config::{load_effective_for, CONFIG};
 use crate::{crab_err, crab_log, crab_print, crab_status};
 use std::io::ErrorKind;
 
 pub struct CrabFind {
     path: String,
+    // профиль сборки: от него зависят ${CRAB_PROFILE} и ${CRAB_TARGET_DIR} в [libraries] path
+    profile: String,
+    // каталог data-файлов детекта (Include/Lib/Pkg)
+    data: PathBuf,
 }
 
 impl CrabFind {
-    pub fn new(path: &str) -> Self {
-        CrabFind { path: path.to_string()}
+    pub fn new(path: &str, profile: &str) -> Self {
+        CrabFind { path: path.to_string(), profile: profile.to_string(), data: PathBuf::from(CONFIG.build_dir).join(CONFIG.data_dir) }
+    }
+
+    // Детект для модуля: у каждого свои data-файлы в crb/module/<name>/data,
+    // чтобы параллельные сборки модулей (--all-modules) не перезаписывали чужие
+    pub(crate) fn module(name: &str, path: &str, profile: &str) -> Self {
+        let data = PathBuf::from(CONFIG.build_dir).join(CONFIG.module_dir).join(name).join(CONFIG.data_dir);
+        CrabFind { path: path.to_string(), profile: profile.to_string(), data }
+    }
+
+    // Каталог data-файлов, из которого читают флаги сторонних библиотек
+    pub(crate) fn data_dir(&self) -> &Path {
+        &self.data
     }
 
     // Проверка указаны ли путь к библиотеки вручную
     fn is_manually(&self) -> std::io::Result<bool> {
         crab_log!("INFO", "FIND", "Checking for specified third-party libraries");
-        let config: CrabConfig = load_config(CONFIG.config_file)?;
+        let config = load_effective_for(&self.profile)?;
         let is_lib = config.libraries.path;
 
         if is_lib.is_empty() {
@@ -31,7 +47,7 @@ impl CrabFind {
     // Сборка путей к библиотекам
     fn collect_manual_libs(&self, includes: &[String]) -> std::io::Result<()> {
         crab_log!("INFO", "FIND", "The beginning of collecting to the specified libraries");
-        let config: CrabConfig = load_config(CONFIG.config_file)?;
+        let config = load_effective_for(&self.profile)?;
         let paths_from_config: Vec<String> = config.libraries.path;
 
         if paths_from_config.is_empty() {
@@ -123,16 +139,17 @@ impl CrabFind {
             crab_err!(ErrorKind::NotFound, "Couldn't find includes or libs in provided paths!");
         }
 
-        let path_to_write = PathBuf::from(CONFIG.build_dir).join(CONFIG.data_dir);
+        let path_to_write = &self.data;
 
         crab_log!("INFO", "FIND", "Checking the existence of a file for writing specified third-party libraries");
         if !path_to_write.exists() {
             crab_log!("INFO", "FIND", "The file does not exist. Create: {}", path_to_write.display());
-            fs::create_dir_all(&path_to_write)?;
+            fs::create_dir_all(path_to_write)?;
         }
 
         self.write_include_path(&include_path)?;        
         self.write_libs_path(&libs_vec)?;
+        self.write_pkg_names(&[])?;
 
         Ok(())
     }
@@ -140,8 +157,8 @@ impl CrabFind {
     // Свежесть кэша детекта: оба data-файла существуют, непусты и новее всех
     // переданных файлов (исходники/заголовки/конфиг). Иначе нужен передетект.
     fn is_cache_fresh(&self, files: &[String]) -> bool {
-        let inc = PathBuf::from(CONFIG.build_dir).join(CONFIG.data_dir).join(CONFIG.include_file);
-        let lib = PathBuf::from(CONFIG.build_dir).join(CONFIG.data_dir).join(CONFIG.lib_file);
+        let inc = self.data.join(CONFIG.include_file);
+        let lib = self.data.join(CONFIG.lib_file);
 
         let (inc_m, lib_m) = match (fs::metadata(&inc), fs::metadata(&lib)) {
             (Ok(a), Ok(b)) if a.len() > 0 && b.len() > 0 => (a, b),
@@ -167,10 +184,22 @@ impl CrabFind {
 
     // Удаление устаревших data-файлов детекта (когда сторонних библиотек не осталось)
     fn clear_cache(&self) {
-        let inc = PathBuf::from(CONFIG.build_dir).join(CONFIG.data_dir).join(CONFIG.include_file);
-        let lib = PathBuf::from(CONFIG.build_dir).join(CONFIG.data_dir).join(CONFIG.lib_file);
+        let inc = self.data.join(CONFIG.include_file);
+        let lib = self.data.join(CONFIG.lib_file);
         let _ = fs::remove_file(inc);
         let _ = fs::remove_file(lib);
+        let _ = fs::remove_file(self.data.join(CONFIG.pkg_file));
+        let _ = fs::remove_file(self.data.join(CONFIG.libraries_file));
+    }
+
+    // Каталоги [libraries] path, с которыми делался детект: после подстановки переменных
+    // они зависят от профиля, и кэш другого профиля с другими путями не годится
+    fn read_library_dirs(&self) -> Option<String> {
+        fs::read_to_string(self.data.join(CONFIG.libraries_file)).ok()
+    }
+
+    fn write_library_dirs(&self, dirs: &str) -> std::io::Result<()> {
+        fs::write(self.data.join(CONFIG.libraries_file), dirs)
     }
 
     // Рекурсивный сбор файлов по указаному расширению
@@ -526,9 +555,7 @@ impl CrabFind {
 
     // Запись пути к hpp библиотек
     fn write_include_path(&self, include_path: &Vec<String>) -> std::io::Result<()> {
-        let path_to_write = PathBuf::from(CONFIG.build_dir)
-            .join(CONFIG.data_dir)
-            .join(CONFIG.include_file);
+        let path_to_write = self.data.join(CONFIG.include_file);
 
         let mut file = OpenOptions::new()
             .create(true)
@@ -546,9 +573,7 @@ impl CrabFind {
 
     // Запись путей к .a и .so для сторонних библиотек
     fn write_libs_path(&self, lib_path: &Vec<String>) -> std::io::Result<()> {
-        let path_to_write = PathBuf::from(CONFIG.build_dir)
-            .join(CONFIG.data_dir)
-            .join(CONFIG.lib_file);
+        let path_to_write = self.data.join(CONFIG.lib_file);
 
         let mut file = OpenOptions::new()
             .create(true)
@@ -564,14 +589,34 @@ impl CrabFind {
         Ok(())
     }
 
+    // Запись имён пакетов, найденных через pkg-config (для Requires в .pc библиотеки)
+    fn write_pkg_names(&self, names: &[String]) -> std::io::Result<()> {
+        let path_to_write = self.data.join(CONFIG.pkg_file);
+
+        crab_log!("INFO", "FIND", "Writing pkg-config packages: {:?}", names);
+        fs::write(path_to_write, names.iter().map(|n| format!("{}\n", n)).collect::<String>())
+    }
+
+    // Пакеты pkg-config, найденные при последнем детекте
+    pub fn read_pkg_names() -> std::io::Result<Vec<String>> {
+        let path = PathBuf::from(CONFIG.build_dir).join(CONFIG.data_dir).join(CONFIG.pkg_file);
+
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        Ok(fs::read_to_string(path)?.lines().map(str::trim).filter(|l| !l.is_empty()).map(str::to_string).collect())
+    }
+
     // Основная функция парсига стороних библиотек
     pub fn parsing_include(&self) -> std::io::Result<bool> {
         crab_log!("INFO", "FIND", "Starting to build third-party libraries");
 
         let path = Path::new(&self.path);
 
-        let config: CrabConfig = load_config(CONFIG.config_file)?;
+        let config = load_effective_for(&self.profile)?;
         let lang = config.settings.lang;
+        let library_dirs = config.libraries.path.join("\n");
 
         let mut source: Vec<String> = Vec::new();
         let mut header: Vec<String> = Vec::new();
@@ -589,7 +634,7 @@ impl CrabFind {
         let mut watched = source.clone();
         watched.extend(header.clone());
         watched.push(CONFIG.config_file.to_string());
-        if self.is_cache_fresh(&watched) {
+        if self.is_cache_fresh(&watched) && self.read_library_dirs().as_deref() == Some(library_dirs.as_str()) {
             crab_log!("INFO", "FIND", "Third-party cache is up to date");
             return Ok(true);
         }
@@ -613,6 +658,7 @@ impl CrabFind {
 
         if self.is_manually()? {
             self.collect_manual_libs(&sys_includes)?;
+            self.write_library_dirs(&library_dirs)?;
             return Ok(true);
         }
 
@@ -626,6 +672,7 @@ impl CrabFind {
 
         // Каждый пакет проверяем через pkg-config только один раз
         let mut queried: HashSet<String> = HashSet::new();
+        let mut packages: Vec<String> = Vec::new();
 
         for incl_sys in sys_includes {
             // Имя пакета = первый компонент пути (#include <SDL2/SDL.h> → "sdl2")
@@ -645,6 +692,7 @@ impl CrabFind {
             if let Some((pkg_incs, pkg_libs)) = self.try_pkg_config(&pkg_name) {
                 crab_log!("INFO", "FIND", "pkg-config found: {}", pkg_name);
                 crab_status!("Detected", "{} via pkg-config", pkg_name);
+                packages.push(pkg_name.clone());
                 for d in pkg_incs {
                     if !include_vec.contains(&d) {
                         include_vec.push(d);
@@ -668,14 +716,14 @@ impl CrabFind {
             }
         }
 
-        let path_to_data_dir = PathBuf::from(CONFIG.build_dir).join(CONFIG.data_dir);
-
-        if !path_to_data_dir.exists() {
-            fs::create_dir(&path_to_data_dir)?;
+        if !self.data.exists() {
+            fs::create_dir_all(&self.data)?;
         }
 
         self.write_include_path(&include_vec)?;
         self.write_libs_path(&lib_vec)?;
+        self.write_pkg_names(&packages)?;
+        self.write_library_dirs(&library_dirs)?;
 
         crab_log!("INFO", "FIND", "End of the build of third-party libraries");
 
@@ -688,6 +736,15 @@ impl CrabFind {
 mod tests {
     use super::*;
 
+    #[test]
+    fn modules_detect_into_their_own_data_dirs() {
+        let net = CrabFind::module("net", "src/net", "debug");
+        let gui = CrabFind::module("gui", "src/gui", "debug");
+        assert_eq!(net.data_dir(), Path::new("crb").join("module").join("net").join("data"));
+        assert_ne!(net.data_dir(), gui.data_dir());
+        assert_eq!(CrabFind::new(".", "debug").data_dir(), Path::new("crb").join("data"));
+    }
+
     #[test]
     fn delete_sys_include_removes_std_keeps_third_party() {
         // Логирование по умолчанию выключено, поэтому crab_log! внутри не падает
@@ -699,7 +756,7 @@ mod tests {
             "raylib.h".to_string(),
         ];
 
-        CrabFind::new(".").delete_sys_include(&mut v).unwrap();
+        CrabFind::new(".", "debug").delete_sys_include(&mut v).unwrap();
 
         assert!(!v.contains(&"vector".to_string()));
         assert!(!v.contains(&"iostream".to_string()));
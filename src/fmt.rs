@@ -4,7 +4,7 @@ use std::process::{Command, Stdio};
 
 use rayon::prelude::*;
 
-use crate::config::{load_config, CrabConfig, CONFIG};
+use crate::config::load_effective;
 use crate::find::CrabFind;
 use crate::{crab_err, crab_print, crab_log};
 use std::io::ErrorKind;
@@ -46,7 +46,7 @@ impl CrabFmt {
 
     // Сбор файлов C/C++ из каталогов исходников и заголовков
     fn collect_files(&self) -> std::io::Result<Vec<String>> {
-        let config: CrabConfig = load_config(CONFIG.config_file)?;
+        let config = load_effective()?;
         let dirs = [config.settings.source_dir, config.settings.header_dir];
         let exts = ["c", "cc", "cpp", "cxx", "h", "hh", "hpp", "hxx"];
 
@@ -0,0 +1,86 @@
+// Сопоставление путей с glob-шаблонами из config.toml:
+//   *  — любые символы внутри одного компонента пути
+//   ?  — один символ
+//   ** — любое число каталогов (в т.ч. ни одного)
+// Шаблон без "/" сравнивается только с именем файла (как в .gitignore).
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let path = normalize(path);
+    let pattern = normalize(pattern);
+
+    if !pattern.contains('/') {
+        let name = path.rsplit('/').next().unwrap_or(&path);
+        return match_name(pattern.as_bytes(), name.as_bytes());
+    }
+
+    let p: Vec<&str> = pattern.split('/').collect();
+    let s: Vec<&str> = path.split('/').collect();
+    match_segments(&p, &s)
+}
+
+// Есть ли в строке метасимволы glob (иначе это обычный путь)
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+fn normalize(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    path.strip_prefix("./").unwrap_or(&path).to_string()
+}
+
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.first() {
+        None => path.is_empty(),
+        Some(&"**") => (0..=path.len()).any(|i| match_segments(&pattern[1..], &path[i..])),
+        Some(seg) => {
+            !path.is_empty()
+                && match_name(seg.as_bytes(), path[0].as_bytes())
+                && match_segments(&pattern[1..], &path[1..])
+        }
+    }
+}
+
+fn match_name(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            match_name(&pattern[1..], name) || (!name.is_empty() && match_name(pattern, &name[1..]))
+        }
+        (Some(b'?'), Some(_)) => match_name(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => match_name(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn double_star_matches_any_depth() {
+        assert!(glob_match("src/third_party/**", "src/third_party/a.cpp"));
+        assert!(glob_match("src/third_party/**", "src/third_party/x/y/z.cpp"));
+        assert!(glob_match("**/*.c", "src/a/b.c"));
+        assert!(glob_match("src/**/net.cpp", "src/net.cpp"));
+        assert!(!glob_match("src/third_party/**", "src/main.cpp"));
+    }
+
+    #[test]
+    fn single_star_stays_within_a_component() {
+        assert!(glob_match("src/*.cpp", "src/main.cpp"));
+        assert!(!glob_match("src/*.cpp", "src/net/main.cpp"));
+        assert!(glob_match("src/ma?n.cpp", "src/main.cpp"));
+    }
+
+    #[test]
+    fn pattern_without_slash_matches_file_name() {
+        assert!(glob_match("hot_loop.cpp", "src/math/hot_loop.cpp"));
+        assert!(glob_match("*.S", "src/asm/memcpy.S"));
+        assert!(!glob_match("*.S", "src/asm/memcpy.s"));
+    }
+
+    #[test]
+    fn leading_dot_slash_is_ignored() {
+        assert!(glob_match("./src/*.cpp", "src/main.cpp"));
+        assert!(glob_match("src/*.cpp", "./src/main.cpp"));
+    }
+}
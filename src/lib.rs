@@ -5,6 +5,7 @@ pub mod log;
 pub mod color;
 pub mod find;
 pub mod fmt;
+pub mod glob;
 pub mod build;
 pub mod project;
 pub mod module;
@@ -1,13 +1,19 @@
 use std::{fs, path::{Path, PathBuf}};
-use crate::config::{load_config, save_config, CrabConfig, Module, CONFIG};
-use crate::find::CrabFind;
-use crate::build::CrabBuild;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::time::Instant;
+use rayon::prelude::*;
+use crate::config::{load_effective_for, remove_modules, save_module, sync_module_sources, CrabConfig, Module, ToolchainEnv, CONFIG};
+use crate::build::{BuildProfile, CrabBuild, CrabBuildFunc, CrabLib, LibKind};
+use crate::color::paint;
 use crate::{crab_err, crab_log, crab_print, crab_status};
 use std::io::ErrorKind;
 
+// Имя основного проекта в uses: его статическая библиотека
+pub(crate) const CORE: &str = "core";
 
-
-pub struct CrabModule;
+pub struct CrabModule {
+    unity: bool,
+}
 
 impl Default for CrabModule {
     fn default() -> Self {
@@ -17,7 +23,13 @@ impl Default for CrabModule {
 
 impl CrabModule {
     pub fn new() -> Self {
-        CrabModule
+        CrabModule { unity: false }
+    }
+
+    // crab build --unity: передаётся сборке core и модулей
+    pub fn with_unity(mut self, unity: bool) -> Self {
+        self.unity = unity;
+        self
     }
 
     fn search_dir(&self, src: &Path, name: &str) -> std::io::Result<Option<PathBuf>> {
@@ -46,10 +58,9 @@ impl CrabModule {
 
     pub fn create(&self, name: &str) -> std::io::Result<()> {   
         crab_log!("INFO", "MODULE", "Creating a module");
-        let mut config: CrabConfig = load_config(CONFIG.config_file)?;
+        let config = load_effective_for(CONFIG.debug_dir)?;
         let src = &config.settings.source_dir;
         let src_path = Path::new(&src);
-        let mut cpp: Vec<String> = Vec::new();
 
         if config.module.contains_key(name) {
             crab_log!("ERROR", "MODULE", "Module {} already exists", name);
@@ -57,18 +68,14 @@ impl CrabModule {
         }
 
         if let Some(dir) = self.search_dir(src_path, name)? {
-
-            CrabFind::collect_file_with_extension(&dir, "cpp", &mut cpp)?;
-
-            let module = Module {
+            let mut module = Module {
                 path: dir.display().to_string(),
-                dependencies: cpp,
                 output_name: Some(name.to_string()),
+                ..Module::default()
             };
+            module.dependencies = module_sources(&config, &module)?.unwrap_or_default();
 
-            config.module.insert(name.to_string(), module);
-
-            save_config(&config, CONFIG.config_file)?;
+            save_module(CONFIG.config_file, name, &module)?;
 
             crab_status!("Added", "module {}", name);
 
@@ -84,12 +91,16 @@ impl CrabModule {
 
     pub fn remove(&self, name: &str) -> std::io::Result<()> {
         crab_log!("INFO", "MODULE", "Deleting a module");
-        let mut config: CrabConfig = load_config(CONFIG.config_file)?;
-        
-        config.module.remove(name)
-        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, format!("Module {} not found", name)))?;
+        let config = load_effective_for(CONFIG.debug_dir)?;
+
+        if !config.module.contains_key(name) {
+            crab_err!(ErrorKind::NotFound, "Module {} not found", name);
+        }
 
-        save_config(&config, CONFIG.config_file)?;
+        // [files] модуля остаются: их снимет crab module prune, если каталог исчезнет
+        if !remove_modules(CONFIG.config_file, &[(name.to_string(), None)])?.is_empty() {
+            crab_err!(ErrorKind::Unsupported, "Module {} is inherited through extends, remove it from the base config", name);
+        }
 
         let path_to_model = PathBuf::from(CONFIG.build_dir).join(CONFIG.module_dir).join(name);
 
@@ -106,35 +117,646 @@ impl CrabModule {
         Ok(())
     }
 
+    // Удаление модулей, чьих каталогов больше нет: запись в config.toml,
+    // их файлы из [files] и артефакты сборки
+    pub fn prune(&self) -> std::io::Result<()> {
+        let mut config = load_effective_for(CONFIG.debug_dir)?;
+
+        let mut dead: Vec<String> = config.module.iter()
+            .filter(|(_, m)| !Path::new(&m.path).is_dir())
+            .map(|(name, _)| name.clone())
+            .collect();
+        dead.sort();
+
+        if dead.is_empty() {
+            crab_print!("No modules to prune");
+            return Ok(());
+        }
+
+        let removed: Vec<(String, Option<String>)> = dead.iter().map(|name| (name.clone(), Some(config.module[name].path.clone()))).collect();
+        let inherited = remove_modules(CONFIG.config_file, &removed)?;
+
+        for name in &dead {
+            let module = config.module.remove(name).unwrap_or_default();
+
+            if inherited.contains(name) {
+                crab_print!(yellow, "warning: module {} ({} no longer exists) is inherited through extends, prune it in the base config", name, module.path);
+                continue;
+            }
+
+            let build = PathBuf::from(CONFIG.build_dir).join(CONFIG.module_dir).join(name);
+            if build.exists() {
+                fs::remove_dir_all(&build)?;
+            }
+
+            crab_print!(red, "{:>12} module {} ({} no longer exists)", "Pruned", name, module.path);
+        }
+
+        // оставшиеся модули, которые ссылались на удалённые, перестанут собираться
+        let mut users: Vec<(&String, &String)> = config.module.iter()
+            .flat_map(|(name, m)| m.uses.iter().filter(|u| dead.contains(u)).map(move |u| (name, u)))
+            .collect();
+        users.sort();
+        for (name, used) in users {
+            crab_print!(yellow, "warning: module {} still uses pruned module {}", name, used);
+        }
+
+        Ok(())
+    }
+
+    // Обновление списка исходников модуля (dependencies) и [files] перед сборкой
+    // (новые исходники сразу попадают в [files], чтобы сборка не писала config.toml)
+    fn sync_sources(&self, config: &CrabConfig, name: &str) -> std::io::Result<()> {
+        sync_module_sources(CONFIG.config_file, name, &resolve_sources(config, name)?)
+    }
+
+    // Таблица модулей: каталог, число исходников, имя результата и его свежесть в debug/release
+    pub fn list(&self) -> std::io::Result<()> {
+        // каждый профиль со своими переменными (${CRAB_PROFILE} в path и т.п.)
+        let config = load_effective_for(&BuildProfile::Debug.dir())?;
+        let release = load_effective_for(&BuildProfile::Release.dir())?;
+
+        if config.module.is_empty() {
+            crab_print!("No modules registered, add one with `crab module add <NAME>`");
+            return Ok(());
+        }
+
+        let mut rows = vec![["MODULE", "PATH", "SOURCES", "OUTPUT", "DEBUG", "RELEASE"].map(String::from)];
+
+        for name in config.module.keys().collect::<BTreeSet<_>>() {
+            rows.push(list_row(&config, &release, name)?);
+        }
+
+        print_table(&rows);
+        Ok(())
+    }
+
+    // Сборка всех модулей: волнами по глубине uses, модули одной волны — параллельно.
+    // Ошибка модуля (в том числе при обновлении его исходников) или core не останавливает
+    // остальные: она попадает в итоговую таблицу, зависящие от него модули пропускаются.
+    pub fn build_all(&self, profile: BuildProfile) -> std::io::Result<()> {
+        let start = Instant::now();
+        let config = load_effective_for(&profile.dir())?;
+
+        if config.module.is_empty() {
+            crab_err!(ErrorKind::NotFound, "No modules registered, add one with `crab module add <NAME>`");
+        }
+
+        let waves = waves(&config)?;
+        let used: HashSet<&str> = config.module.values().flat_map(|m| m.uses.iter().map(String::as_str)).collect();
+
+        let mut results: Vec<(String, Outcome)> = Vec::new();
+
+        for name in waves.iter().flatten() {
+            if let Err(e) = self.sync_sources(&config, name) {
+                results.push((name.clone(), Outcome::Failed(e.to_string())));
+            }
+        }
+
+        if used.contains(CORE)
+            && let Err(e) = CrabLib::new().with_unity(self.unity).build_lib(LibKind::Static, profile.clone()) {
+                results.push((CORE.to_string(), Outcome::Failed(e.to_string())));
+            }
+
+        for wave in &waves {
+            // модули, упавшие ещё до сборки, уже в results
+            let wave: Vec<&String> = wave.iter().filter(|n| !results.iter().any(|(r, _)| r == *n)).collect();
+
+            let done: Vec<(String, Outcome)> = wave.par_iter().map(|name| {
+                if let Some(dep) = failed_dependency(&config, name, &results) {
+                    return (name.to_string(), Outcome::Skipped(dep));
+                }
+
+                let start = Instant::now();
+                let built = (|| {
+                    if used.contains(name.as_str()) {
+                        CrabBuild::new().with_unity(self.unity).building(profile.clone(), Some(name), None)?;
+                    }
+                    CrabBuild::new().with_unity(self.unity).building(profile.clone(), Some(name), Some(&config.module[*name].bin_name(name)))
+                })();
+
+                match built {
+                    Ok(()) => (name.to_string(), Outcome::Built(start.elapsed().as_secs_f64())),
+                    Err(e) => (name.to_string(), Outcome::Failed(e.to_string())),
+                }
+            }).collect();
+
+            results.extend(done);
+        }
+
+        let mut rows = vec![["MODULE", "RESULT", "TIME"].map(String::from)];
+        for (name, result) in &results {
+            rows.push(match result {
+                Outcome::Built(time) => [name.clone(), "ok".to_string(), format!("{:.2}s", time)],
+                Outcome::Failed(e) => [name.clone(), format!("failed: {}", e.lines().next().unwrap_or_default()), String::new()],
+                Outcome::Skipped(dep) => [name.clone(), format!("skipped: {} failed", dep), String::new()],
+            });
+        }
+        print_table(&rows);
+
+        let failed = results.iter().filter(|(_, r)| matches!(r, Outcome::Failed(_))).count();
+        let skipped = results.iter().filter(|(_, r)| matches!(r, Outcome::Skipped(_))).count();
+        if failed > 0 {
+            crab_err!(ErrorKind::Other, "{} of {} modules failed to build, {} skipped", failed, results.len(), skipped);
+        }
+
+        crab_status!("Finished", "{} modules [{}] in {:.2}s", results.len(), profile.dir(), start.elapsed().as_secs_f64());
+        Ok(())
+    }
+
+    // Сборка модуля: сначала всё из uses (в порядке зависимостей, каждое — один раз),
+    // затем сам модуль, слинкованный с их архивами
     pub fn build_module(&mut self, name: &str, flag: &str) -> std::io::Result<()> {
         crab_log!("INFO", "MODULE", "Starting the module build");
-        let mut config: CrabConfig = load_config(CONFIG.config_file)?;
-        let bin_name = config.module
-            .get(name)
-            .and_then(|m| m.output_name.as_ref().filter(|s| !s.is_empty()).cloned())
-            .unwrap_or_else(|| name.to_string());
+        let profile = if flag == "debug" { BuildProfile::Debug } else { BuildProfile::Release };
+        let config = load_effective_for(&profile.dir())?;
 
-        let source_dir = PathBuf::from(config.settings.source_dir.clone()).join(name);
-        let lang = if config.settings.lang == "c" { "c" } else { "cpp" };
+        if !config.module.contains_key(name) {
+            crab_err!(ErrorKind::NotFound, "Module {} not found", name);
+        }
 
-        let mut files_vec = Vec::new();
-        CrabFind::collect_file_with_extension(&source_dir, lang, &mut files_vec)?;
+        let bin_name = config.module[name].bin_name(name);
 
-        if let Some(module) = config.module.get_mut(name) {
-            module.dependencies = files_vec;
-        }
-        save_config(&config, CONFIG.config_file)?;
-    
-        if flag == "debug" {
-            CrabBuild::new().debug_building(Some(name), Some(&bin_name))?;
-        } else {
-            CrabBuild::new().release_building(Some(name), Some(&bin_name))?;
+        for dep in build_order(&config, name)? {
+            if dep == CORE {
+                CrabLib::new().with_unity(self.unity).build_lib(LibKind::Static, profile.clone())?;
+            } else {
+                self.sync_sources(&config, &dep)?;
+                CrabBuild::new().with_unity(self.unity).building(profile.clone(), Some(&dep), None)?;
+            }
         }
 
+        self.sync_sources(&config, name)?;
+        CrabBuild::new().with_unity(self.unity).building(profile, Some(name), Some(&bin_name))?;
+
         crab_log!("INFO", "MODULE", "Module build is complete");
 
         Ok(())
     }
+}
+
+// Итог сборки модуля в crab build --all-modules
+enum Outcome {
+    Built(f64),      // время сборки, с
+    Failed(String),  // текст ошибки
+    Skipped(String), // упавшая зависимость
+}
+
+// Зависимость модуля из uses, которая не собралась (упала или сама пропущена)
+fn failed_dependency(config: &CrabConfig, name: &str, results: &[(String, Outcome)]) -> Option<String> {
+    build_order(config, name).unwrap_or_default().into_iter()
+        .find(|d| results.iter().any(|(n, r)| n == d && !matches!(r, Outcome::Built(_))))
+}
+
+// Волны сборки --all-modules: модули одной волны не зависят друг от друга,
+// а всё из их uses собирается в предыдущих волнах
+fn waves(config: &CrabConfig) -> std::io::Result<Vec<Vec<String>>> {
+    let levels = levels(config)?;
+    let max = levels.values().copied().max().unwrap_or(0);
+
+    Ok((1..=max).map(|level| levels.iter().filter(|(_, l)| **l == level).map(|(n, _)| n.clone()).collect()).collect())
+}
+
+// Глубина каждого модуля в графе uses: 1 — без модулей-зависимостей, core — 0
+fn levels(config: &CrabConfig) -> std::io::Result<BTreeMap<String, usize>> {
+    let mut levels: BTreeMap<String, usize> = BTreeMap::new();
+
+    for name in config.module.keys() {
+        let mut order = build_order(config, name)?;
+        order.push(name.clone());
+
+        for m in order {
+            let level = match config.module.get(&m) {
+                Some(module) if m != CORE => 1 + module.uses.iter().map(|u| levels.get(u).copied().unwrap_or(0)).max().unwrap_or(0),
+                _ => 0,
+            };
+            levels.insert(m, level);
+        }
+    }
+
+    levels.remove(CORE);
+    Ok(levels)
+}
+
+// Исходники модуля в его каталоге path на языке модуля — по тем же правилам, что и
+// исходники проекта (без каталога сборки); None — каталога больше нет
+fn module_sources(config: &CrabConfig, module: &Module) -> std::io::Result<Option<Vec<String>>> {
+    let dir = Path::new(&module.path);
+    if !dir.is_dir() {
+        return Ok(None);
+    }
+
+    let lang = module.lang.as_deref().unwrap_or(&config.settings.lang);
+    let mut sources = Vec::new();
+    CrabBuildFunc::collect_sources(dir, lang, &mut sources)?;
+    sources.sort();
+    Ok(Some(sources))
+}
+
+// Исходники модуля на момент сборки; исчезнувший каталог — понятная ошибка
+pub(crate) fn resolve_sources(config: &CrabConfig, name: &str) -> std::io::Result<Vec<String>> {
+    let Some(module) = config.module.get(name) else {
+        crab_err!(ErrorKind::NotFound, "Module {} not found", name);
+    };
+
+    match module_sources(config, module)? {
+        Some(sources) => Ok(sources),
+        None => crab_err!(ErrorKind::NotFound,
+            "The directory of module {} no longer exists: {} (update its path in {} or drop dead modules with `crab module prune`)",
+            name, module.path, CONFIG.config_file),
+    }
+}
+
+// Строка crab module list: имя, каталог, число исходников, результат, свежесть в debug/release
+// (путь и исходники — по конфигу debug, свежесть — по конфигу своего профиля)
+fn list_row(debug: &CrabConfig, release: &CrabConfig, name: &str) -> std::io::Result<[String; 6]> {
+    let module = &debug.module[name];
+    let sources = module_sources(debug, module)?;
+    let count = sources.as_ref().map_or("missing".to_string(), |s| s.len().to_string());
+    let output = module_output(debug, name, &BuildProfile::Debug, Some(&module.bin_name(name)));
+    let output = output.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string();
+
+    // без каталога свежесть не определить
+    let state = |config: &CrabConfig, profile: BuildProfile| -> std::io::Result<String> {
+        Ok(match config.module.get(name).map(|m| module_sources(config, m)).transpose()?.flatten() {
+            Some(sources) => output_state(config, name, &profile, &sources),
+            None => "-".to_string(),
+        })
+    };
+
+    Ok([name.to_string(), module.path.clone(), count, output, state(debug, BuildProfile::Debug)?, state(release, BuildProfile::Release)?])
+}
+
+// Свежесть результата модуля в профиле: not built / stale / up to date
+fn output_state(config: &CrabConfig, name: &str, profile: &BuildProfile, sources: &[String]) -> String {
+    let module = &config.module[name];
+    let lang = module.lang.as_deref().unwrap_or(&config.settings.lang);
+    let output = module_output(config, name, profile, Some(&module.bin_name(name)));
+    let path_dep = PathBuf::from(CONFIG.build_dir).join(CONFIG.module_dir).join(name).join(profile.dir()).join(CONFIG.dependencies);
+
+    freshness(&output, &path_dep, sources, lang).to_string()
+}
+
+fn freshness(output: &Path, path_dep: &Path, sources: &[String], lang: &str) -> &'static str {
+    if !output.exists() {
+        "not built"
+    } else if CrabBuildFunc::new().is_output_fresh(output, path_dep, sources, lang) {
+        "up to date"
+    } else {
+        "stale"
+    }
+}
+
+// Таблица с выравниванием по столбцам; первая строка — заголовок
+fn print_table<const N: usize>(rows: &[[String; N]]) {
+    let widths: Vec<usize> = (0..N).map(|i| rows.iter().map(|r| r[i].chars().count()).max().unwrap_or(0)).collect();
+
+    for (i, row) in rows.iter().enumerate() {
+        let line = row.iter().zip(&widths).map(|(cell, w)| format!("{:<w$}", cell, w = w)).collect::<Vec<_>>().join("  ");
+        let line = line.trim_end();
+
+        if i == 0 {
+            crab_print!("{}", paint("1", line));
+        } else {
+            crab_print!("{}", line);
+        }
+    }
+}
+
+// Порядок сборки всего, что модуль использует (транзитивно, сначала самые глубокие),
+// без самого модуля. Статические архивы линкуются в обратном порядке.
+pub(crate) fn build_order(config: &CrabConfig, name: &str) -> std::io::Result<Vec<String>> {
+    let mut order = Vec::new();
+    visit(config, name, &mut Vec::new(), &mut order)?;
+    order.pop();
+    Ok(order)
+}
+
+fn visit(config: &CrabConfig, name: &str, stack: &mut Vec<String>, order: &mut Vec<String>) -> std::io::Result<()> {
+    if order.iter().any(|o| o == name) {
+        return Ok(());
+    }
+
+    if let Some(pos) = stack.iter().position(|s| s == name) {
+        let mut cycle = stack[pos..].to_vec();
+        cycle.push(name.to_string());
+        crab_err!(ErrorKind::InvalidInput, "Module dependency cycle: {}", cycle.join(" -> "));
+    }
+
+    if name == CORE {
+        order.push(CORE.to_string());
+        return Ok(());
+    }
+
+    let Some(module) = config.module.get(name) else {
+        match stack.last() {
+            Some(user) => crab_err!(ErrorKind::NotFound, "Module {} (used by {}) not found", name, user),
+            None => crab_err!(ErrorKind::NotFound, "Module {} not found", name),
+        }
+    };
+
+    stack.push(name.to_string());
+    for dep in &module.uses {
+        visit(config, dep, stack, order)?;
+    }
+    stack.pop();
+
+    order.push(name.to_string());
+    Ok(())
+}
+
+// Архив модуля, собранного как зависимость: crb/module/<name>/<профиль>/lib<name>.a
+pub(crate) fn module_archive(name: &str, profile: &BuildProfile) -> PathBuf {
+    PathBuf::from(CONFIG.build_dir).join(CONFIG.module_dir).join(name).join(profile.dir()).join(format!("lib{}.a", name))
+}
+
+// Конфиг сборки модуля: его defines/cflags/ldflags и -I на каталоги используемых
+// модулей поверх [build], свой язык (с компилятором этого языка), -fPIC/-shared для shared
+pub(crate) fn module_config(mut config: CrabConfig, name: &str) -> std::io::Result<CrabConfig> {
+    let Some(module) = config.module.get(name).cloned() else {
+        crab_err!(ErrorKind::NotFound, "Module {} not found", name);
+    };
+
+    if !["bin", "static", "shared"].contains(&module.kind()) {
+        crab_err!(ErrorKind::InvalidInput, "Unknown kind of module {}: {} (expected bin, static or shared)", name, module.kind());
+    }
+
+    let mut overlay = module.build_overlay();
+
+    for dep in build_order(&config, name)? {
+        match config.module.get(&dep) {
+            Some(used) if dep != CORE => overlay.include_dirs.push(used.path.clone()),
+            _ => overlay.cflags.extend(LibKind::Static.consumer_args(&config)),
+        }
+    }
+
+    if module.kind() == "shared" {
+        if !cfg!(windows) {
+            overlay.cflags.push("-fPIC".to_string());
+        }
+        overlay.ldflags.push("-shared".to_string());
+    }
+
+    if let Some(lang) = module.lang.as_deref() {
+        if lang != "c" && lang != "c++" {
+            crab_err!(ErrorKind::InvalidInput, "Unknown lang of module {}: {} (expected c or c++)", name, lang);
+        }
+        if lang != config.settings.lang {
+            switch_lang(&mut config, lang, &ToolchainEnv::from_env());
+        }
+    }
+
+    config.build.merge(&overlay);
+    Ok(config)
+}
+
+// Перевод конфига проекта на другой язык модуля. Компилятор — из CC/CXX или того же семейства;
+// CFLAGS/CXXFLAGS окружения пересчитываются под язык модуля, а стандарт и PCH проекта
+// к другому языку неприменимы — стандарт модуль задаёт сам (standard в [module.*]).
+fn switch_lang(config: &mut CrabConfig, lang: &str, env: &ToolchainEnv) {
+    config.settings.compiler = env.compiler(lang).map(str::to_string)
+        .unwrap_or_else(|| compiler_for(&config.settings.compiler, lang));
+
+    // ToolchainEnv::apply дописал флаги окружения в конец [build] cflags
+    let inherited = env.compile_args(&config.settings.lang);
+    if config.build.cflags.ends_with(&inherited) {
+        let len = config.build.cflags.len() - inherited.len();
+        config.build.cflags.truncate(len);
+    }
+    config.build.cflags.extend(env.compile_args(lang));
 
+    config.build.standard.clear();
+    config.build.pch.clear();
+    config.settings.lang = lang.to_string();
+}
+
+// Компилятор того же семейства для другого языка: gcc <-> g++, clang <-> clang++, cc <-> c++.
+// Суффикс версии сохраняется: clang++-17 -> clang-17, g++-13 -> gcc-13.
+fn compiler_for(compiler: &str, lang: &str) -> String {
+    let pairs = [("clang", "clang++"), ("gcc", "g++"), ("cc", "c++")];
+    let (dir, file) = compiler.rsplit_once('/').map_or(("", compiler), |(d, f)| (d, f));
+    let (file, version) = match file.rsplit_once('-') {
+        Some((name, v)) if !v.is_empty() && v.chars().all(|c| c.is_ascii_digit() || c == '.') => (name, &file[name.len()..]),
+        _ => (file, ""),
+    };
+
+    let swapped = pairs.iter().find_map(|(c, cpp)| {
+        let (from, to) = if lang == "c" { (cpp, c) } else { (c, cpp) };
+        file.strip_suffix(from).map(|prefix| format!("{}{}{}", prefix, to, version))
+    });
+
+    match swapped {
+        Some(f) if dir.is_empty() => f,
+        Some(f) => format!("{}/{}", dir, f),
+        None => compiler.to_string(),
+    }
+}
+
+// Модуль, собираемый без bin_name (как зависимость из uses) или с kind = "static", — архив
+pub(crate) fn builds_archive(config: &CrabConfig, name: &str, bin_name: Option<&str>) -> bool {
+    bin_name.is_none() || config.module.get(name).is_some_and(|m| m.kind() == "static")
+}
+
+// Файл, который даёт сборка модуля: исполняемый, разделяемая библиотека или архив
+pub(crate) fn module_output(config: &CrabConfig, name: &str, profile: &BuildProfile, bin_name: Option<&str>) -> PathBuf {
+    if builds_archive(config, name, bin_name) {
+        return module_archive(name, profile);
+    }
+
+    let file = match (bin_name.unwrap_or(name), config.module.get(name).is_some_and(|m| m.kind() == "shared")) {
+        (b, true) => format!("{}{}{}", std::env::consts::DLL_PREFIX, b, std::env::consts::DLL_SUFFIX),
+        (b, false) => format!("{}{}", b, std::env::consts::EXE_SUFFIX),
+    };
+
+    PathBuf::from(CONFIG.build_dir).join(CONFIG.module_dir).join(name).join(profile.dir()).join(CONFIG.binary_dir).join(file)
+}
+
+// Архивы uses в порядке линковки (зависимые раньше своих зависимостей)
+pub(crate) fn uses_outputs(config: &CrabConfig, name: &str, profile: &BuildProfile) -> std::io::Result<Vec<PathBuf>> {
+    Ok(build_order(config, name)?
+        .iter()
+        .rev()
+        .map(|dep| if dep == CORE { LibKind::Static.output_file(config, profile) } else { module_archive(dep, profile) })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(modules: &str) -> CrabConfig {
+        let text = format!(
+            "[project]\nname = \"app\"\nversion = \"0.1.0\"\ncreated = 2026\n\n[settings]\nlang = \"c++\"\ncompiler = \"g++\"\nsource_dir = \"src\"\nheader_dir = \"include\"\n\n{}",
+            modules
+        );
+        toml::from_str(&text).unwrap()
+    }
+
+    #[test]
+    fn build_order_puts_dependencies_first_once() {
+        let cfg = config("[module.app]\npath = \"src/app\"\nuses = [\"net\", \"core\"]\n\n[module.net]\npath = \"src/net\"\nuses = [\"core\"]\n");
+
+        assert_eq!(build_order(&cfg, "app").unwrap(), vec!["core", "net"]);
+        assert_eq!(build_order(&cfg, "net").unwrap(), vec!["core"]);
+    }
+
+    #[test]
+    fn build_order_reports_cycles_and_unknown_modules() {
+        let cfg = config("[module.a]\npath = \"src/a\"\nuses = [\"b\"]\n\n[module.b]\npath = \"src/b\"\nuses = [\"a\"]\n");
+        let err = build_order(&cfg, "a").unwrap_err();
+        assert!(err.to_string().contains("a -> b -> a"));
+
+        let cfg = config("[module.a]\npath = \"src/a\"\nuses = [\"ghost\"]\n");
+        assert!(build_order(&cfg, "a").unwrap_err().to_string().contains("used by a"));
+    }
+
+    #[test]
+    fn waves_build_dependencies_before_dependents() {
+        let cfg = config("[module.app]\npath = \"src/app\"\nuses = [\"net\", \"core\"]\n\n\
+            [module.net]\npath = \"src/net\"\nuses = [\"log\"]\n\n\
+            [module.log]\npath = \"src/log\"\n\n[module.tool]\npath = \"src/tool\"\nuses = [\"core\"]\n");
+
+        // core собирается отдельно, до первой волны
+        assert_eq!(waves(&cfg).unwrap(), vec![vec!["log", "tool"], vec!["net"], vec!["app"]]);
 
+        let cfg = config("[module.a]\npath = \"src/a\"\nuses = [\"b\"]\n\n[module.b]\npath = \"src/b\"\nuses = [\"a\"]\n");
+        assert!(waves(&cfg).is_err());
+    }
+
+    #[test]
+    fn failed_core_or_module_skips_dependents() {
+        let cfg = config("[module.app]\npath = \"src/app\"\nuses = [\"net\"]\n\n\
+            [module.net]\npath = \"src/net\"\nuses = [\"core\"]\n\n[module.tool]\npath = \"src/tool\"\n");
+
+        // core не собрался — net и app не собираются, tool собирается
+        let results = vec![(CORE.to_string(), Outcome::Failed("no sources".to_string()))];
+        assert_eq!(failed_dependency(&cfg, "net", &results).as_deref(), Some(CORE));
+        assert_eq!(failed_dependency(&cfg, "app", &results).as_deref(), Some(CORE));
+        assert_eq!(failed_dependency(&cfg, "tool", &results), None);
+
+        // исходники net не обновились — app пропускается из-за net
+        let results = vec![(CORE.to_string(), Outcome::Built(0.1)), ("net".to_string(), Outcome::Failed("gone".to_string()))];
+        assert_eq!(failed_dependency(&cfg, "app", &results).as_deref(), Some("net"));
+    }
+
+    #[test]
+    fn list_reports_missing_and_unbuilt_modules() {
+        let dir = std::env::temp_dir().join(format!("crab_test_module_list_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.cpp"), "").unwrap();
+
+        let name = format!("list_{}", std::process::id());
+        let cfg = config(&format!("[module.{}]\npath = {:?}\n\n[module.gone]\npath = \"src/gone\"\n", name, dir.display().to_string()));
+
+        let row = list_row(&cfg, &cfg, &name).unwrap();
+        assert_eq!(row[2], "1");
+        assert_eq!(row[3], format!("{}{}", name, std::env::consts::EXE_SUFFIX));
+        assert_eq!([row[4].as_str(), row[5].as_str()], ["not built", "not built"]);
+
+        let row = list_row(&cfg, &cfg, "gone").unwrap();
+        assert_eq!([row[2].as_str(), row[4].as_str(), row[5].as_str()], ["missing", "-", "-"]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn freshness_compares_output_with_sources() {
+        let dir = std::env::temp_dir().join(format!("crab_test_module_fresh_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("a.cpp");
+        let output = dir.join("app");
+        let path_dep = dir.join("deps.d");
+        let sources = vec![source.display().to_string()];
+        fs::write(&source, "").unwrap();
+        fs::write(&path_dep, format!("a.o: {}\n", source.display())).unwrap();
+
+        assert_eq!(freshness(&output, &path_dep, &sources, "c++"), "not built");
+
+        fs::write(&output, "").unwrap();
+        let now = std::time::SystemTime::now();
+        fs::File::options().write(true).open(&source).unwrap().set_modified(now - std::time::Duration::from_secs(60)).unwrap();
+        assert_eq!(freshness(&output, &path_dep, &sources, "c++"), "up to date");
+
+        // исходник правили после сборки
+        fs::File::options().write(true).open(&source).unwrap().set_modified(now + std::time::Duration::from_secs(60)).unwrap();
+        assert_eq!(freshness(&output, &path_dep, &sources, "c++"), "stale");
+
+        // новый исходник, которого ещё нет в файле зависимостей
+        let mut more = sources.clone();
+        more.push(dir.join("b.cpp").display().to_string());
+        fs::File::options().write(true).open(&source).unwrap().set_modified(now - std::time::Duration::from_secs(60)).unwrap();
+        assert_eq!(freshness(&output, &path_dep, &more, "c++"), "stale");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn module_config_merges_flags_over_build() {
+        let cfg = config("[build]\ndefines = [\"APP\"]\n\n[module.net]\npath = \"src/net\"\ndefines = [\"NET\"]\nldflags = [\"-lm\"]\nkind = \"shared\"\nlang = \"c\"\n");
+        let net = module_config(cfg, "net").unwrap();
+
+        assert_eq!(net.build.defines, vec!["APP", "NET"]);
+        assert!(net.build.cflags.contains(&"-fPIC".to_string()) || cfg!(windows));
+        assert_eq!(net.build.ldflags, vec!["-lm", "-shared"]);
+        assert_eq!(net.settings.lang, "c");
+
+        let cfg = config("[module.net]\npath = \"src/net\"\nkind = \"dll\"\n");
+        assert!(module_config(cfg, "net").is_err());
+    }
+
+    #[test]
+    fn resolve_sources_follows_path_and_language() {
+        let dir = std::env::temp_dir().join(format!("crab_test_module_{}", std::process::id()));
+        fs::create_dir_all(dir.join("deep")).unwrap();
+        fs::write(dir.join("a.c"), "").unwrap();
+        fs::write(dir.join("deep").join("b.c"), "").unwrap();
+        fs::write(dir.join("c.cpp"), "").unwrap();
+
+        let cfg = config(&format!("[module.m]\npath = {:?}\nlang = \"c\"\n", dir.display().to_string()));
+        let sources = resolve_sources(&cfg, "m").unwrap();
+        assert_eq!(sources.len(), 2);
+        assert!(sources.iter().all(|s| s.ends_with(".c")));
+
+        fs::remove_dir_all(&dir).unwrap();
+        let err = resolve_sources(&cfg, "m").unwrap_err();
+        assert!(err.to_string().contains("no longer exists"));
+    }
+
+    #[test]
+    fn compiler_for_swaps_within_family() {
+        assert_eq!(compiler_for("g++", "c"), "gcc");
+        assert_eq!(compiler_for("x86_64-w64-mingw32-gcc", "c++"), "x86_64-w64-mingw32-g++");
+        assert_eq!(compiler_for("/usr/bin/clang", "c++"), "/usr/bin/clang++");
+        assert_eq!(compiler_for("clang++", "c"), "clang");
+        assert_eq!(compiler_for("icx", "c"), "icx");
+        assert_eq!(compiler_for("clang++-17", "c"), "clang-17");
+        assert_eq!(compiler_for("/usr/bin/g++-13", "c"), "/usr/bin/gcc-13");
+        assert_eq!(compiler_for("aarch64-linux-gnu-gcc-12.2", "c++"), "aarch64-linux-gnu-g++-12.2");
+    }
+
+    #[test]
+    fn c_module_in_cpp_project_drops_cpp_settings() {
+        let mut cfg = config("[build]
+standard = \"c++17\"\npch = \"include/pch.hpp\"\ncflags = [\"-Wall\"]\n\n\
+            [module.codec]\npath = \"src/codec\"\nlang = \"c\"\n");
+        let env = crate::config::ToolchainEnv::from_lookup(|k| match k {
+            "CPPFLAGS" => Some("-DENV".to_string()),
+            "CXXFLAGS" => Some("-fno-rtti".to_string()),
+            "CFLAGS" => Some("-O3".to_string()),
+            _ => None,
+        });
+        env.apply(&mut cfg);
+        assert_eq!(cfg.build.cflags, vec!["-Wall", "-DENV", "-fno-rtti"]);
+
+        switch_lang(&mut cfg, "c", &env);
+        assert_eq!(cfg.settings.compiler, "gcc");
+        assert_eq!(cfg.settings.lang, "c");
+        assert_eq!(cfg.build.cflags, vec!["-Wall", "-DENV", "-O3"]);
+        assert!(cfg.build.standard.is_empty() && cfg.build.pch.is_empty());
+
+        // свой стандарт модуля накладывается поверх
+        let cfg = config("[build]
+standard = \"c++17\"\n\n[module.codec]\npath = \"src/codec\"\nlang = \"c\"\nstandard = \"c11\"\n");
+        assert_eq!(module_config(cfg, "codec").unwrap().build.standard, "c11");
+    }
 }
\ No newline at end of file
@@ -47,12 +47,21 @@ impl CrabProject {
     // Заполнение конфигурационого файла.
     // src_file — имя начального исходника для секции [files] (None → default main.c/cpp; для init не важно)
     pub fn init_config(&self, project_name: &str, is_new: bool, lang: &str, src_file: Option<&str>) -> std::io::Result<()> {
+        let config = self.new_config(project_name, is_new, lang, src_file)?;
+        self.save_new_config(&config, project_name, is_new)
+    }
+
+    fn save_new_config(&self, config: &CrabConfig, project_name: &str, is_new: bool) -> std::io::Result<()> {
         let path_to_config =  if is_new {
             PathBuf::from(project_name).join(CONFIG.config_file)
         } else {
             PathBuf::from(CONFIG.config_file)
         };
 
+        save_config(config, path_to_config.display().to_string().as_str())
+    }
+
+    fn new_config(&self, project_name: &str, is_new: bool, lang: &str, src_file: Option<&str>) -> std::io::Result<CrabConfig> {
 
         let is_compiler = Self::checking_compilers()?;
 
@@ -71,6 +80,7 @@ impl CrabProject {
         };
 
         let config = CrabConfig {
+            extends: None,
             project: Project {
                 name: project_name.to_string(),
                 version: "0.0.1".to_string(),
@@ -82,6 +92,7 @@ impl CrabProject {
                 compiler: compiler.to_string(),
                 source_dir: "src".to_string(),
                 header_dir: "include".to_string(),
+                target: None,
             },
 
             build: Build::default(),
@@ -100,12 +111,14 @@ impl CrabProject {
                 path: vec![],
             },
 
+            library: Default::default(),
+            bins: Vec::new(),
+
             module: HashMap::new(),
+            target: Default::default(),
         };
 
-        save_config(&config, path_to_config.display().to_string().as_str())?;
-
-        Ok(())
+        Ok(config)
     }
 
     // Создание библиотечного проекта: src/<name>.c(pp) + include/<name>.h(pp), без main()
@@ -165,6 +178,51 @@ impl CrabProject {
         Ok(())
     }
 
+    // Создание header-only библиотеки: только include/<name>.h(pp) с inline-реализацией
+    pub fn create_header_only(&self, git: bool, lang: &str) -> std::io::Result<()> {
+        let name = &self.name;
+        let inc_dir     = PathBuf::from(name).join("include");
+        let build_dir   = PathBuf::from(name).join(CONFIG.build_dir);
+
+        fs::create_dir_all(&inc_dir)?;
+        fs::create_dir_all(&build_dir)?;
+
+        let (hdr_file, hdr_code) = if lang == "c++" {
+            let hdr_code = format!(
+                "#pragma once\n\n#include <iostream>\n\nnamespace {name} {{\n\ninline void hello() {{\n\tstd::cout << \"Hello from {name}!\" << std::endl;\n}}\n\n}}  // namespace {name}\n",
+                name = name
+            );
+            (format!("{}.hpp", name), hdr_code)
+        } else {
+            let hdr_code = format!(
+                "#pragma once\n\n#include <stdio.h>\n\nstatic inline void {name}_hello(void) {{\n\tprintf(\"Hello from {name}!\\n\");\n}}\n",
+                name = name
+            );
+            (format!("{}.h", name), hdr_code)
+        };
+
+        fs::write(inc_dir.join(&hdr_file), &hdr_code)?;
+
+        let mut config = self.new_config(name, true, lang, None)?;
+        config.files.clear();
+        config.library.kind = Some("header-only".to_string());
+        self.save_new_config(&config, name, true)?;
+
+        crab_status!("Created", "{} header-only library `{}`", lang, name);
+
+        if git {
+            if self.is_git() {
+                std::env::set_current_dir(name)?;
+                Command::new("git").arg("init").stdout(Stdio::null()).stderr(Stdio::null()).status()?;
+                fs::write(".gitignore", format!("{}/\n", CONFIG.build_dir))?;
+            } else {
+                crab_print!(yellow, "Git is not installed on your computer!");
+            }
+        }
+
+        Ok(())
+    }
+
     // Создание проекта
     pub fn create(&self, git: bool, lang: &str, cli: bool) -> std::io::Result<()> {
         // Создание путей
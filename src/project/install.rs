@@ -1,9 +1,13 @@
+use std::ffi::OsStr;
 use std::fs;
 use std::io::ErrorKind;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::config::{load_config, CrabConfig, CONFIG};
-use crate::build::{BuildProfile, CrabBuild};
+use crate::config::{load_effective_for, CrabConfig, CONFIG};
+use crate::build::{is_entry_point, BuildProfile, CrabBuild, CrabBuildFunc, CrabCheck, CrabLib, LibKind, PkgConfig, SharedNames};
+use crate::find::CrabFind;
+use crate::glob::glob_match;
+use super::manifest::write_manifest;
 use crate::{crab_err, crab_log, crab_print, crab_status};
 
 pub struct CrabInstall;
@@ -32,13 +36,28 @@ impl CrabInstall {
 
     // Собрать проект и скопировать бинарник в целевой каталог
     pub fn install(&self, dest: Option<&str>, debug: bool) -> std::io::Result<()> {
-        let config: CrabConfig = load_config(CONFIG.config_file)?;
-        let name = config.project.name;
-
         let profile = if debug { BuildProfile::Debug } else { BuildProfile::Release };
+        let config = load_effective_for(&profile.dir())?;
+        let name = config.project.name.clone();
+
+        if config.library.is_header_only() {
+            crab_err!(ErrorKind::InvalidInput, "{} is a header-only library, install its headers with `crab install --prefix <dir>`", name);
+        }
+
+        // исполняемым файлам нужна динамическая библиотека рядом (../lib) — это раскладка --prefix
+        match LibKind::from_config(&config) {
+            None => {}
+            Some(_) if config.bins.is_empty() => {
+                crab_err!(ErrorKind::InvalidInput, "{} builds only a library, install it with `crab install --prefix <dir>`", name);
+            }
+            Some(LibKind::Dynamic) => {
+                crab_err!(ErrorKind::InvalidInput, "{} links its shared library, install it with `crab install --prefix <dir>`", name);
+            }
+            Some(LibKind::Static) => {}
+        }
 
         // Инкрементальная сборка
-        CrabBuild::new().building(profile.clone(), None, None)?;
+        CrabBuild::new().build_all_targets(profile.clone())?;
 
         // Целевой каталог
         let bin_dir = match dest {
@@ -50,40 +69,285 @@ impl CrabInstall {
             std::io::Error::new(e.kind(), format!("Cannot create {}: {}", bin_dir.display(), e))
         })?;
 
-        let exe = std::env::consts::EXE_SUFFIX;
-        let bin_name = format!("{}{}", name, exe);
-        let src = PathBuf::from(CONFIG.build_dir)
-            .join(profile.dir())
-            .join(CONFIG.binary_dir)
-            .join(&bin_name);
-        let dst = bin_dir.join(&bin_name);
+        let mut installed = Vec::new();
+
+        for bin_name in binary_names(&config) {
+            let src = PathBuf::from(CONFIG.build_dir)
+                .join(profile.dir())
+                .join(CONFIG.binary_dir)
+                .join(&bin_name);
+            let dst = bin_dir.join(&bin_name);
+
+            if !src.exists() {
+                crab_err!(ErrorKind::NotFound, "Binary not found: {}", src.display());
+            }
+
+            crab_log!("INFO", "INSTALL", "copy {} -> {}", src.display(), dst.display());
+            fs::copy(&src, &dst)?;
+
+            // Устанавливаем биты исполнения на Unix
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let mut perms = fs::metadata(&dst)?.permissions();
+                perms.set_mode(0o755);
+                fs::set_permissions(&dst, perms)?;
+            }
+
+            crab_status!("Installing", "{} -> {}", bin_name, dst.display());
+            installed.push(std::path::absolute(&dst)?);
+        }
+
+        let bin_dir_abs = std::path::absolute(&bin_dir)?;
+        write_manifest(&name, &config.project.version, &bin_dir_abs, &bin_dir_abs, &installed)?;
 
+        // Предупреждение если каталог не в PATH
+        let in_path = std::env::var_os("PATH")
+            .is_some_and(|p| std::env::split_paths(&p).any(|d| d == bin_dir));
+
+        if !in_path {
+            crab_print!(yellow, "note: {} is not in your PATH", bin_dir.display());
+        }
+
+        Ok(())
+    }
+
+    // Каталог назначения с учётом DESTDIR (staged-установка для пакетирования):
+    // DESTDIR=/tmp/stage + /opt/foo -> /tmp/stage/opt/foo
+    fn staged(prefix: &Path, destdir: Option<&OsStr>) -> PathBuf {
+        match destdir.filter(|d| !d.is_empty()) {
+            Some(destdir) => {
+                let relative: PathBuf = prefix.components()
+                    .filter(|c| !matches!(c, std::path::Component::RootDir | std::path::Component::Prefix(_)))
+                    .collect();
+                PathBuf::from(destdir).join(relative)
+            }
+            None => prefix.to_path_buf(),
+        }
+    }
+
+    // Копирование файла с созданием каталогов; executable — права 0755 на Unix
+    fn copy_file(src: &Path, dst: &Path, executable: bool) -> std::io::Result<()> {
         if !src.exists() {
-            crab_err!(ErrorKind::NotFound, "Binary not found: {}", src.display());
+            crab_err!(ErrorKind::NotFound, "File not found: {}", src.display());
+        }
+
+        if let Some(parent) = dst.parent() {
+            fs::create_dir_all(parent).map_err(|e| {
+                std::io::Error::new(e.kind(), format!("Cannot create {}: {}", parent.display(), e))
+            })?;
+        }
+
+        // поверх символической ссылки копировать нельзя — она указывает на старую версию
+        if dst.symlink_metadata().is_ok() {
+            fs::remove_file(dst)?;
         }
 
         crab_log!("INFO", "INSTALL", "copy {} -> {}", src.display(), dst.display());
-        fs::copy(&src, &dst)?;
+        fs::copy(src, dst)?;
 
-        // Устанавливаем биты исполнения на Unix
         #[cfg(unix)]
-        {
+        if executable {
             use std::os::unix::fs::PermissionsExt;
-            let mut perms = fs::metadata(&dst)?.permissions();
+            let mut perms = fs::metadata(dst)?.permissions();
             perms.set_mode(0o755);
-            fs::set_permissions(&dst, perms)?;
+            fs::set_permissions(dst, perms)?;
         }
+        #[cfg(not(unix))]
+        let _ = executable;
 
-        crab_status!("Installing", "{} -> {}", bin_name, dst.display());
+        Ok(())
+    }
 
-        // Предупреждение если каталог не в PATH
-        let in_path = std::env::var_os("PATH")
-            .is_some_and(|p| std::env::split_paths(&p).any(|d| d == bin_dir));
+    // Установка в prefix: bin/ (исполняемый файл), lib/ (статическая и динамическая
+    // библиотеки с цепочкой ссылок), include/ (публичные заголовки) и lib/pkgconfig/.
+    // Проект без main() считается библиотекой, с main() — приложением;
+    // header-only библиотека ставит только include/ и share/pkgconfig/.
+    pub fn install_prefix(&self, prefix: &str, debug: bool) -> std::io::Result<()> {
+        let profile = if debug { BuildProfile::Debug } else { BuildProfile::Release };
+        let config = load_effective_for(&profile.dir())?;
 
-        if !in_path {
-            crab_print!(yellow, "note: {} is not in your PATH", bin_dir.display());
+        let prefix = std::path::absolute(prefix)?;
+        let root = Self::staged(&prefix, std::env::var_os("DESTDIR").as_deref());
+
+        let mut sources = Vec::new();
+        CrabBuildFunc::collect_sources(Path::new(&config.settings.source_dir), &config.settings.lang, &mut sources)?;
+
+        let mut files = Vec::new();
+
+        if config.library.is_header_only() {
+            self.install_header_only(&config, &profile, &prefix, &root, &mut files)?;
+        } else if let Some(kind) = LibKind::from_config(&config) {
+            // библиотека проекта и её исполняемые цели
+            self.install_libraries(&config, &profile, &[kind], &prefix, &root, &mut files)?;
+            if !config.bins.is_empty() {
+                self.install_binary(&config, &profile, &root.join("bin"), &mut files)?;
+            }
+        } else if !config.bins.is_empty() || sources.iter().any(|s| is_entry_point(s)) {
+            self.install_binary(&config, &profile, &root.join("bin"), &mut files)?;
+        } else {
+            self.install_libraries(&config, &profile, &[LibKind::Static, LibKind::Dynamic], &prefix, &root, &mut files)?;
+        }
+
+        // при staged-установке (DESTDIR) манифест хранит пути без DESTDIR
+        write_manifest(&config.project.name, &config.project.version, &prefix, &root, &files)?;
+
+        crab_status!("Installed", "{} v{} -> {}", config.project.name, config.project.version, root.display());
+
+        Ok(())
+    }
+
+    fn install_binary(&self, config: &CrabConfig, profile: &BuildProfile, bin_dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+        CrabBuild::new().build_all_targets(profile.clone())?;
+
+        for bin_name in binary_names(config) {
+            let src = PathBuf::from(CONFIG.build_dir).join(profile.dir()).join(CONFIG.binary_dir).join(&bin_name);
+            let dst = bin_dir.join(&bin_name);
+
+            Self::copy_file(&src, &dst, true)?;
+            crab_status!("Installing", "{}", dst.display());
+            files.push(dst);
+        }
+
+        Ok(())
+    }
+
+    fn install_libraries(&self, config: &CrabConfig, profile: &BuildProfile, kinds: &[LibKind], prefix: &Path, root: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+        let lib = CrabLib::new();
+        for kind in kinds {
+            lib.build_lib(*kind, profile.clone())?;
+        }
+
+        let name = config.library.output_name(&config.project.name);
+        let lib_dir = root.join("lib");
+
+        // статическая
+        if kinds.contains(&LibKind::Static) {
+            let archive = format!("lib{}.a", name);
+            Self::copy_file(&LibKind::Static.output_dir(profile).join(&archive), &lib_dir.join(&archive), false)?;
+            crab_status!("Installing", "{}", lib_dir.join(&archive).display());
+            files.push(lib_dir.join(&archive));
+        }
+
+        // динамическая: на Windows DLL кладётся в bin/, рядом с исполняемыми
+        if kinds.contains(&LibKind::Dynamic) {
+            let names = SharedNames::new(&name, &config.project.version);
+            let shared_dir = if cfg!(windows) { root.join("bin") } else { lib_dir.clone() };
+            Self::copy_file(&LibKind::Dynamic.output_dir(profile).join(&names.real), &shared_dir.join(&names.real), true)?;
+            names.link_chain(&shared_dir)?;
+            crab_status!("Installing", "{}", shared_dir.join(&names.real).display());
+            for f in [&names.real, &names.soname, &names.link] {
+                let path = shared_dir.join(f);
+                if !files.contains(&path) {
+                    files.push(path);
+                }
+            }
+        }
+
+        // .pc описывает динамическую, если она есть (как и для pkg-config по умолчанию)
+        let pc_kind = if kinds.contains(&LibKind::Dynamic) { LibKind::Dynamic } else { LibKind::Static };
+
+        self.install_headers(config, root, files)?;
+        self.install_pc(config, pc_kind, prefix, &lib_dir.join("pkgconfig"), files)
+    }
+
+    // header-only: только заголовки и .pc (в share/pkgconfig — не зависит от архитектуры)
+    fn install_header_only(&self, config: &CrabConfig, profile: &BuildProfile, prefix: &Path, root: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+        CrabCheck::new().check_headers(config, profile)?;
+
+        self.install_headers(config, root, files)?;
+        self.install_pc(config, LibKind::Dynamic, prefix, &root.join("share").join("pkgconfig"), files)
+    }
+
+    fn install_headers(&self, config: &CrabConfig, root: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+        let include_dir = root.join("include");
+        let headers = public_headers(config)?;
+        for h in &headers {
+            let rel = Path::new(h).strip_prefix(&config.settings.header_dir).unwrap_or(Path::new(h));
+            Self::copy_file(Path::new(h), &include_dir.join(rel), false)?;
+            files.push(include_dir.join(rel));
         }
+        crab_status!("Installing", "{} headers -> {}", headers.len(), include_dir.display());
 
         Ok(())
     }
+
+    // pkg-config: пути — относительно настоящего prefix (без DESTDIR)
+    fn install_pc(&self, config: &CrabConfig, kind: LibKind, prefix: &Path, pc_dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+        let pc = PkgConfig::from_project(config, kind)?;
+        let content = pc.render(prefix, &prefix.join("lib"), &prefix.join("include"));
+        let (pc_path, _) = pc.write(pc_dir, &content)?;
+        crab_status!("Installing", "{}", pc_path.display());
+        files.push(pc_path);
+
+        Ok(())
+    }
+}
+
+// Заголовки из header_dir, подходящие под [library].public_headers (по умолчанию — все)
+fn public_headers(config: &CrabConfig) -> std::io::Result<Vec<String>> {
+    let dir = Path::new(&config.settings.header_dir);
+    let mut headers = Vec::new();
+
+    if !dir.is_dir() {
+        return Ok(headers);
+    }
+
+    for ext in ["h", "hpp", "hh", "hxx", "inl"] {
+        CrabFind::collect_file_with_extension(dir, ext, &mut headers)?;
+    }
+
+    let patterns = &config.library.public_headers;
+    headers.retain(|h| patterns.is_empty() || patterns.iter().any(|p| glob_match(p, h)));
+    headers.sort();
+    headers.dedup();
+
+    Ok(headers)
+}
+
+// Имена устанавливаемых исполняемых файлов: цели [[bin]] или сам проект
+fn binary_names(config: &CrabConfig) -> Vec<String> {
+    let exe = std::env::consts::EXE_SUFFIX;
+    if config.bins.is_empty() {
+        vec![format!("{}{}", config.project.name, exe)]
+    } else {
+        config.bins.iter().map(|b| format!("{}{}", b.name, exe)).collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::manifest::{checksum, installed_files};
+
+    #[test]
+    fn destdir_stages_files_and_manifest_keeps_prefix_paths() {
+        let destdir = std::env::temp_dir().join(format!("crab_test_destdir_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&destdir);
+        let prefix = Path::new("/opt/foo");
+
+        assert_eq!(CrabInstall::staged(prefix, None), prefix);
+        assert_eq!(CrabInstall::staged(prefix, Some(OsStr::new(""))), prefix);
+        let root = CrabInstall::staged(prefix, Some(destdir.as_os_str()));
+        assert_eq!(root, destdir.join("opt").join("foo"));
+
+        let src = destdir.join("build.out");
+        fs::create_dir_all(&destdir).unwrap();
+        fs::write(&src, "payload").unwrap();
+
+        let files: Vec<PathBuf> = ["bin/app", "lib/libfoo.a", "include/foo/foo.h"].iter().map(|f| root.join(f)).collect();
+        for f in &files {
+            CrabInstall::copy_file(&src, f, false).unwrap();
+            assert!(f.starts_with(&destdir) && f.exists());
+        }
+
+        let entries = installed_files(prefix, &root, &files).unwrap();
+        let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+        let expected: Vec<String> = ["bin/app", "lib/libfoo.a", "include/foo/foo.h"].iter().map(|f| prefix.join(f).display().to_string()).collect();
+        assert_eq!(paths, expected);
+        assert!(entries.iter().all(|e| e.checksum == checksum(&src).unwrap()));
+
+        let _ = fs::remove_dir_all(&destdir);
+    }
 }
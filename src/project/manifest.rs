@@ -0,0 +1,196 @@
+use std::fs;
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+
+use crate::config::{load_config, save_config, InstallManifest, InstalledFile};
+use crate::crab_log;
+
+// Каталог манифестов: $XDG_DATA_HOME/crab/installs или ~/.local/share/crab/installs;
+// на Windows — %APPDATA%\crab\installs
+pub(crate) fn manifest_dir() -> std::io::Result<PathBuf> {
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .filter(|d| !d.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local").join("share")))
+    };
+
+    let base = base.ok_or_else(|| std::io::Error::new(ErrorKind::NotFound,
+        "Cannot determine user data directory (XDG_DATA_HOME / HOME / APPDATA not set)"))?;
+
+    Ok(base.join("crab").join("installs"))
+}
+
+// FNV-1a 64 — быстрая контрольная сумма для обнаружения изменённых файлов (не криптографическая)
+pub(crate) fn checksum(path: &Path) -> std::io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut buf = [0u8; 64 * 1024];
+    let mut hash: u64 = 0xcbf29ce484222325;
+
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        for b in &buf[..n] {
+            hash ^= *b as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    Ok(format!("{:016x}", hash))
+}
+
+// Запись об установленном файле: ссылка запоминается целью, обычный файл — суммой
+pub(crate) fn record(path: &Path) -> std::io::Result<InstalledFile> {
+    let path_str = path.display().to_string();
+
+    if path.symlink_metadata()?.file_type().is_symlink() {
+        let target = fs::read_link(path)?.display().to_string();
+        return Ok(InstalledFile { path: path_str, checksum: String::new(), link: Some(target) });
+    }
+
+    Ok(InstalledFile { path: path_str, checksum: checksum(path)?, link: None })
+}
+
+// Имя файла манифеста: проект + хэш каталога установки (разные prefix — разные манифесты)
+fn manifest_name(project: &str, prefix: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in prefix.bytes() {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{}-{:08x}.toml", project, hash as u32)
+}
+
+// Записи манифеста: файлы лежат под root (с DESTDIR — в staging-каталоге),
+// а записываются путями под prefix — там, где они окажутся после установки пакета
+pub(crate) fn installed_files(prefix: &Path, root: &Path, files: &[PathBuf]) -> std::io::Result<Vec<InstalledFile>> {
+    files.iter().map(|f| {
+        let mut entry = record(f)?;
+        if let Ok(relative) = f.strip_prefix(root) {
+            entry.path = prefix.join(relative).display().to_string();
+        }
+        Ok(entry)
+    }).collect()
+}
+
+// Сохранение манифеста установки. Повторная установка в тот же prefix дополняет прежний
+// манифест: файлы прошлой установки, которых нет в новой, остаются под учётом crab uninstall.
+pub(crate) fn write_manifest(project: &str, version: &str, prefix: &Path, root: &Path, files: &[PathBuf]) -> std::io::Result<PathBuf> {
+    let dir = manifest_dir()?;
+    fs::create_dir_all(&dir)?;
+
+    let fresh = installed_files(prefix, root, files)?;
+    let prefix = prefix.display().to_string();
+    let path = dir.join(manifest_name(project, &prefix));
+
+    let previous = match load_config::<InstallManifest>(&path.display().to_string()) {
+        Ok(m) => m.files,
+        Err(e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+        Err(e) => {
+            crab_log!("WARNING", "INSTALL", "Replacing unreadable manifest {}: {}", path.display(), e);
+            Vec::new()
+        }
+    };
+
+    let manifest = InstallManifest {
+        project: project.to_string(),
+        version: version.to_string(),
+        prefix: prefix.clone(),
+        installed: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        files: merge_files(previous, fresh),
+    };
+
+    save_config(&manifest, &path.display().to_string())?;
+    crab_log!("INFO", "INSTALL", "Wrote install manifest: {}", path.display());
+
+    Ok(path)
+}
+
+// Новые записи заменяют прежние с тем же путём; прежние, чьих файлов уже нет, отбрасываются
+fn merge_files(previous: Vec<InstalledFile>, fresh: Vec<InstalledFile>) -> Vec<InstalledFile> {
+    let mut files: Vec<InstalledFile> = previous.into_iter()
+        .filter(|old| !fresh.iter().any(|f| f.path == old.path) && Path::new(&old.path).symlink_metadata().is_ok())
+        .collect();
+    files.extend(fresh);
+    files
+}
+
+// Все манифесты проекта: (путь к файлу манифеста, манифест)
+pub(crate) fn find_manifests(project: &str) -> std::io::Result<Vec<(PathBuf, InstallManifest)>> {
+    let dir = manifest_dir()?;
+    let mut found = Vec::new();
+
+    if !dir.is_dir() {
+        return Ok(found);
+    }
+
+    for entry in fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|e| e != "toml") {
+            continue;
+        }
+
+        match load_config::<InstallManifest>(&path.display().to_string()) {
+            Ok(m) if m.project == project => found.push((path, m)),
+            Ok(_) => {}
+            Err(e) => crab_log!("WARNING", "INSTALL", "Skipping unreadable manifest {}: {}", path.display(), e),
+        }
+    }
+
+    found.sort_by(|a, b| a.1.prefix.cmp(&b.1.prefix));
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksum_changes_with_content() {
+        let dir = std::env::temp_dir().join(format!("crab_test_checksum_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("a.txt");
+
+        fs::write(&file, "hello").unwrap();
+        let first = checksum(&file).unwrap();
+        assert_eq!(first, "a430d84680aabd0b"); // FNV-1a 64 от "hello"
+
+        fs::write(&file, "hello!").unwrap();
+        assert_ne!(checksum(&file).unwrap(), first);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reinstall_merges_manifest_entries() {
+        let dir = std::env::temp_dir().join(format!("crab_test_manifest_merge_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let old_tool = dir.join("old-tool");
+        let app = dir.join("app");
+        fs::write(&old_tool, "v1").unwrap();
+        fs::write(&app, "v1").unwrap();
+        let gone = InstalledFile { path: dir.join("gone").display().to_string(), checksum: String::new(), link: None };
+        let previous = vec![record(&old_tool).unwrap(), record(&app).unwrap(), gone];
+
+        fs::write(&app, "v2").unwrap();
+        let merged = merge_files(previous, vec![record(&app).unwrap()]);
+
+        let paths: Vec<&str> = merged.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, [old_tool.display().to_string(), app.display().to_string()]);
+        assert_eq!(merged[1].checksum, checksum(&app).unwrap());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn manifest_name_depends_on_prefix() {
+        assert_ne!(manifest_name("app", "/opt/a"), manifest_name("app", "/opt/b"));
+        assert!(manifest_name("app", "/opt/a").starts_with("app-"));
+    }
+}
@@ -4,6 +4,8 @@ mod clean;
 mod tree;
 mod install;
 mod watch;
+mod manifest;
+mod uninstall;
 
 pub use create::CrabProject;
 pub use run::CrabRun;
@@ -11,3 +13,4 @@ pub use clean::CrabClean;
 pub use tree::CrabTree;
 pub use install::CrabInstall;
 pub use watch::CrabWatch;
+pub use uninstall::CrabUninstall;
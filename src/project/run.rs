@@ -1,7 +1,8 @@
 use std::path::PathBuf;
 use std::process::Command;
 
-use crate::config::{load_config, CrabConfig, CONFIG};
+use crate::build::LibKind;
+use crate::config::{load_effective_for, CONFIG};
 use crate::{crab_err, crab_log, crab_status};
 use std::io::ErrorKind;
 
@@ -18,10 +19,24 @@ impl CrabRun {
         CrabRun
     }
     // Запуск исполняемого файла
-    pub fn run(&self, flag: &str, args: &mut Vec<String>, gdb: bool, valgrind: bool) -> std::io::Result<()> {
+    // bin — цель [[bin]] (по умолчанию — цель default, иначе сам проект)
+    pub fn run(&self, flag: &str, bin: Option<&str>, args: &mut Vec<String>, gdb: bool, valgrind: bool) -> std::io::Result<()> {
         crab_log!("INFO", "RUN", "Start running an executable file");
-        let config: CrabConfig = load_config(CONFIG.config_file)?;
-        let exe_name = format!("{}{}", config.project.name, std::env::consts::EXE_SUFFIX);
+        let config = load_effective_for(flag)?;
+
+        if config.library.is_header_only() {
+            crab_err!(ErrorKind::Unsupported, "{} is a header-only library, there is nothing to run", config.project.name);
+        }
+
+        let target = match bin.or(config.default_bin().map(|b| b.name.as_str())) {
+            Some(name) => name.to_string(),
+            None if LibKind::from_config(&config).is_some() => {
+                crab_err!(ErrorKind::Unsupported, "{} builds only a library, add a [[bin]] target to run", config.project.name);
+            }
+            None => config.project.name.clone(),
+        };
+
+        let exe_name = format!("{}{}", target, std::env::consts::EXE_SUFFIX);
 
         let path_to_bin = PathBuf::from(CONFIG.build_dir).join(flag).join(CONFIG.binary_dir).join(&exe_name);
 
@@ -58,11 +73,15 @@ impl CrabRun {
     pub fn run_module(&self, name: &str, flag: &str, args: &mut Vec<String>, gdb: bool, valgrind: bool) -> std::io::Result<()> {
         crab_log!("INFO", "RUN", "Start running an executable file");
 
-        let config: CrabConfig = load_config(CONFIG.config_file)?;
+        let config = load_effective_for(flag)?;
         let module = config.module.get(name)
         .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, format!("Module {} not found", name)))?;
 
-        let bin_name = module.output_name.as_ref().filter(|s| !s.is_empty()).cloned().unwrap_or_else(|| name.to_string());
+        if module.kind() != "bin" {
+            crab_err!(ErrorKind::Unsupported, "Module {} is a {} library, nothing to run", name, module.kind());
+        }
+
+        let bin_name = module.bin_name(name);
         let exe_name = format!("{}{}", bin_name, std::env::consts::EXE_SUFFIX);
 
         let path_to_mod_bin = match flag {
@@ -3,7 +3,7 @@ use std::fs;
 use std::io::BufRead;
 use std::path::{Path, PathBuf};
 
-use crate::config::{load_config, CrabConfig, CONFIG};
+use crate::config::load_effective;
 
 pub struct CrabTree {
     deps: HashMap<String, Vec<String>>,
@@ -122,7 +122,7 @@ impl CrabTree {
     // Cтроим дерево
     pub fn tree(&mut self) -> std::io::Result<()> {
 
-        let config: CrabConfig = load_config(CONFIG.config_file)?;
+        let config = load_effective()?;
 
         let mut c: Vec<String> = Vec::new();
         let mut h: Vec<String> = Vec::new();
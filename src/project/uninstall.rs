@@ -0,0 +1,167 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crate::config::{load_effective, save_config, InstalledFile, CONFIG};
+use crate::{crab_err, crab_log, crab_print, crab_status};
+use super::manifest::{checksum, find_manifests};
+
+pub struct CrabUninstall;
+
+impl Default for CrabUninstall {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CrabUninstall {
+    pub fn new() -> Self {
+        CrabUninstall
+    }
+
+    // Удаление файлов, записанных в манифесте установки.
+    // name — проект (по умолчанию из config.toml), prefix — выбор установки, если их несколько.
+    // Изменённые после установки файлы не удаляются без force.
+    pub fn uninstall(&self, name: Option<&str>, prefix: Option<&str>, force: bool) -> std::io::Result<()> {
+        let project = match name {
+            Some(n) => n.to_string(),
+            None if Path::new(CONFIG.config_file).exists() => load_effective()?.project.name,
+            None => crab_err!(ErrorKind::InvalidInput, "Not in a project: pass the project name, e.g. `crab uninstall myapp`"),
+        };
+
+        let mut manifests = find_manifests(&project)?;
+
+        if let Some(p) = prefix {
+            let p = std::path::absolute(p)?.display().to_string();
+            manifests.retain(|(_, m)| m.prefix == p);
+        }
+
+        let (manifest_path, mut manifest) = match manifests.len() {
+            0 => crab_err!(ErrorKind::NotFound, "No installation of {} found", project),
+            1 => manifests.remove(0),
+            _ => {
+                for (_, m) in &manifests {
+                    crab_print!(yellow, "{:>12} {} v{} in {}", "Installed", m.project, m.version, m.prefix);
+                }
+                crab_err!(ErrorKind::InvalidInput, "{} is installed in several places, choose one with --prefix", project);
+            }
+        };
+
+        let (kept, removed, failed) = remove_installed(&manifest.files, Path::new(&manifest.prefix), force);
+
+        if kept.is_empty() {
+            fs::remove_file(&manifest_path)?;
+        } else {
+            // оставшиеся файлы можно удалить позже через --force
+            manifest.files = kept;
+            save_config(&manifest, &manifest_path.display().to_string())?;
+        }
+
+        if failed > 0 {
+            crab_err!(ErrorKind::Other, "{} files of {} could not be removed, they stay in the install manifest", failed, manifest.project);
+        }
+
+        crab_status!("Uninstalled", "{} v{} from {} ({} files)", manifest.project, manifest.version, manifest.prefix, removed);
+
+        Ok(())
+    }
+}
+
+// Удаление файлов манифеста: (оставшиеся записи, число удалённых, число ошибок).
+// Каталог на месте файла не трогается и с force; ошибка удаления не прерывает остальные,
+// а запись остаётся в манифесте.
+fn remove_installed(files: &[InstalledFile], prefix: &Path, force: bool) -> (Vec<InstalledFile>, usize, usize) {
+    let mut kept: Vec<InstalledFile> = Vec::new();
+    let mut removed = 0;
+    let mut failed = 0;
+
+    for file in files {
+        let path = Path::new(&file.path);
+
+        let Ok(meta) = path.symlink_metadata() else {
+            crab_log!("INFO", "UNINSTALL", "Already missing: {}", path.display());
+            continue;
+        };
+
+        if meta.is_dir() {
+            crab_print!(yellow, "warning: {} is now a directory, keeping it", path.display());
+            kept.push(file.clone());
+            continue;
+        }
+
+        let modified = match &file.link {
+            Some(target) => fs::read_link(path).map_or(true, |t| t.display().to_string() != *target),
+            None => checksum(path).map_or(true, |c| c != file.checksum),
+        };
+
+        if modified && !force {
+            crab_print!(yellow, "warning: {} was modified since installation, keeping it (use --force to remove)", path.display());
+            kept.push(file.clone());
+            continue;
+        }
+
+        if let Err(e) = fs::remove_file(path) {
+            crab_print!(red, "error: cannot remove {}: {}", path.display(), e);
+            kept.push(file.clone());
+            failed += 1;
+            continue;
+        }
+
+        crab_status!("Removing", "{}", path.display());
+        removed += 1;
+
+        remove_empty_parents(path, prefix);
+    }
+
+    (kept, removed, failed)
+}
+
+// Удаление опустевших каталогов вверх от файла, не выходя за пределы prefix
+fn remove_empty_parents(file: &Path, prefix: &Path) {
+    let mut dir: Option<PathBuf> = file.parent().map(Path::to_path_buf);
+
+    while let Some(d) = dir {
+        if d == prefix || !d.starts_with(prefix) || fs::remove_dir(&d).is_err() {
+            break;
+        }
+        dir = d.parent().map(Path::to_path_buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::manifest::record;
+
+    #[test]
+    fn directories_and_modified_files_stay_in_manifest() {
+        let dir = std::env::temp_dir().join(format!("crab_test_uninstall_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("lib")).unwrap();
+        let bin = dir.join("app");
+        let lib = dir.join("lib").join("libapp.a");
+        let share = dir.join("share");
+        fs::write(&bin, "bin").unwrap();
+        fs::write(&lib, "lib").unwrap();
+        fs::write(&share, "doc").unwrap();
+        let files: Vec<InstalledFile> = [&bin, &lib, &share].iter().map(|p| record(p).unwrap()).collect();
+
+        // файл заменили каталогом, бинарник поправили после установки
+        fs::remove_file(&share).unwrap();
+        fs::create_dir_all(share.join("doc")).unwrap();
+        fs::write(&bin, "patched").unwrap();
+
+        let (kept, removed, failed) = remove_installed(&files, &dir, false);
+        assert_eq!((removed, failed), (1, 0));
+        assert_eq!(kept.iter().map(|f| f.path.as_str()).collect::<Vec<_>>(), [bin.display().to_string(), share.display().to_string()]);
+        assert!(!dir.join("lib").exists());
+
+        // --force удаляет изменённый файл, но не каталог
+        let (kept, removed, failed) = remove_installed(&kept, &dir, true);
+        assert_eq!((removed, failed), (1, 0));
+        assert_eq!(kept.len(), 1);
+        assert!(!bin.exists() && share.is_dir());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
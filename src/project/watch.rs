@@ -4,7 +4,7 @@ use std::path::Path;
 use std::time::{Duration, SystemTime};
 
 use crate::build::{BuildProfile, CrabBuild};
-use crate::config::{load_config, CrabConfig, CONFIG};
+use crate::config::load_effective;
 use crate::find::CrabFind;
 use crate::{crab_log, crab_print, crab_status};
 
@@ -63,7 +63,7 @@ impl CrabWatch {
     }
 
     pub fn watch(&self, release: bool) -> std::io::Result<()> {
-        let config: CrabConfig = load_config(CONFIG.config_file)?;
+        let config = load_effective()?;
         let source_dir = config.settings.source_dir.clone();
         let header_dir = config.settings.header_dir.clone();
         let lang       = config.settings.lang.clone();